@@ -9,6 +9,7 @@ use buglympics::{BlSpy, Native};
 
 pub mod native;
 use native::NativeVideo;
+use native::gamepad::Gamepads;
 use native::pack::pack_game;
 
 extern crate sdl2;
@@ -27,12 +28,18 @@ fn main() -> Result<(), String>{
     let sdl_context = sdl2::init()?;
     let mut events = sdl_context.event_pump().unwrap();
 
+    // settings
+    let config_file = String::from("./buglympics.cfg");
+    let config = std::fs::read_to_string(&config_file).unwrap_or_default();
+
     // video
     let mut vid = NativeVideo::new(
         &sdl_context,
         String::from("Winter Buglympics / SPYDER"),
         320,
         240,
+        2,
+        false,
     );
 
     let texture_creator = vid.canvas.texture_creator();
@@ -66,7 +73,29 @@ fn main() -> Result<(), String>{
 
     file.read_to_end(&mut buffer).expect("Failed to fill buffer");
     let mut game = BlSpy::new(&buffer);
-    let mut controller = PadControl::new();
+
+    let songs = std::fs::read_to_string("./web/buglympics.songs").unwrap_or_default();
+    game.set_song_data(&songs);
+
+    let effects = std::fs::read_to_string("./web/buglympics.sfx").unwrap_or_default();
+    for problem in game.set_effect_data(&effects).lines() {
+        eprintln!("Effect data: {}", problem);
+    }
+
+    game.set_config_data(&config);
+    let mut window_mode = (game.get_window_scale(), game.get_fullscreen());
+    if let Err(err) = vid.set_mode(window_mode.0, window_mode.1) {
+        eprintln!("Failed to set window mode: {}", err);
+    }
+
+    // gamepads
+    let mut gamepads = Gamepads::new(&sdl_context, game.get_dead_zone());
+
+    // saved progress
+    let save_file = String::from("./buglympics.sav");
+    if let Ok(data) = std::fs::read_to_string(&save_file) {
+        game.set_save_data(&data);
+    }
 
     let timer = Instant::now();
     let mut last = 0.0;
@@ -75,25 +104,54 @@ fn main() -> Result<(), String>{
         
         // exit?
         for event in events.poll_iter() {
-            if let Event::Quit { .. } = event {
-                println!("Exiting");
-                std::process::exit(0);
-            };
+            gamepads.handle_event(&event);
+            match event {
+                Event::Quit { .. } => {
+                    println!("Exiting");
+                    write_file(&save_file, &game.get_save_data());
+                    write_file(&config_file, &game.get_config_data());
+                    std::process::exit(0);
+                }
+                Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => {
+                    game.capture_key(&keycode.name());
+                }
+                _ => {}
+            }
         }
 
         // handle inputs
-        let keys: HashSet<Keycode> = events
+        let keys: HashSet<String> = events
             .keyboard_state()
             .pressed_scancodes()
             .filter_map(Keycode::from_scancode)
+            .map(|keycode| keycode.name())
             .collect();
-        
-        keys_to_pad(&keys, &mut controller);
+
+        let mut controller = PadControl::new();
+        game.keys_to_pad(&keys, &mut controller);
+        // the dead zone can be changed in the options scene
+        gamepads.dead_zone = game.get_dead_zone();
+        gamepads.to_pad(&mut controller);
         game.set_inputs(&controller);
 
         // update game
         game.update();
 
+        // keep settings from the options scene even if the game doesn't
+        // get to quit cleanly
+        if game.take_settings_changed() {
+            write_file(&config_file, &game.get_config_data());
+        }
+
+        // window settings may have changed in the options scene
+        let mode = (game.get_window_scale(), game.get_fullscreen());
+        if mode != window_mode {
+            if let Err(err) = vid.set_mode(mode.0, mode.1) {
+                eprintln!("Failed to set window mode: {}", err);
+            }
+            window_mode = mode;
+        }
+
         // render frame
         game.render_image();
 
@@ -110,44 +168,13 @@ fn main() -> Result<(), String>{
         vid.render_frame(image_data, &mut texture);
         audio_queue.queue(audio_data);
         audio_queue.resume();
-
-        controller.reset();
-        
     }
     Ok(())
 }
 
-fn keys_to_pad(keys: &HashSet<Keycode>, controller: &mut PadControl){
-
-    if keys.contains(&Keycode::Left){
-        controller.left = 255;
-    }
-
-    if keys.contains(&Keycode::Right){
-        controller.right = 255;
-    }
-
-    if keys.contains(&Keycode::Up){
-        controller.up = 255;
-    }
-
-    if keys.contains(&Keycode::Down){
-        controller.down = 255;
-    }
-
-    if keys.contains(&Keycode::Return){
-        controller.start = 255;
-    }
-
-    if keys.contains(&Keycode::Q){
-        controller.b = 255;
-    }
-
-    if keys.contains(&Keycode::Z){
-        controller.a = 255;
-    }
-    
-    if keys.contains(&Keycode::A){
-        controller.x = 255;
+// a failed write is reported rather than losing the rest of the shutdown
+fn write_file(path: &str, data: &str) {
+    if let Err(err) = std::fs::write(path, data) {
+        eprintln!("Failed to write {}: {}", path, err);
     }
 }
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+
+use lentsys::lentsys::LentSysBus;
+use lentsys::ui::text::{Text, TextBox};
+
+use crate::game::save::SaveData;
+use crate::game::state::SpyderEventRecord;
+
+// Things that happen in game which achievements are checked against
+pub enum GameEvent {
+  BuglympicsFinished {
+    event: String,
+    nation: String,
+    jumps: u32,
+    place: Option<usize>,
+  },
+  SpyderFinished {
+    record: SpyderEventRecord,
+  },
+  Swapped {
+    total_swaps: u32,
+  },
+}
+
+// Running totals that span several events
+#[derive(Default)]
+pub struct Progress {
+  pub gold_nations: HashSet<String>,
+}
+
+pub struct Achievement {
+  pub id: String,
+  pub name: String,
+  pub unlocked: bool,
+  pub condition: fn(&GameEvent, &Progress) -> bool,
+}
+
+pub struct Achievements {
+  pub list: Vec<Achievement>,
+  pub progress: Progress,
+  pub toasts: Vec<String>,
+  pub toast_time: u32,
+  pub toast_map: Option<usize>,
+}
+
+impl Default for Achievements {
+  fn default() -> Self {
+    Self {
+      list: vec![
+        Achievement {
+          id: String::from("downhill_no_jump"),
+          name: String::from("FEET ON THE GROUND"),
+          unlocked: false,
+          condition: |game_event, _progress| match game_event {
+            GameEvent::BuglympicsFinished { event, jumps, .. } => {
+              event == "DOWNHILL BIATHLON" && *jumps == 0
+            }
+            _ => false,
+          },
+        },
+        Achievement {
+          id: String::from("no_misses"),
+          name: String::from("SURGICAL STRIKE"),
+          unlocked: false,
+          condition: |game_event, _progress| match game_event {
+            GameEvent::SpyderFinished { record } => {
+              record.success
+                && record.targets_hit == record.targets_total
+                && record.shots_hit == record.shots_fired
+            }
+            _ => false,
+          },
+        },
+        Achievement {
+          id: String::from("gold_every_nation"),
+          name: String::from("CITIZEN OF EARTHROPOD"),
+          unlocked: false,
+          condition: |_game_event, progress| progress.gold_nations.len() >= 3,
+        },
+        Achievement {
+          id: String::from("swap_100"),
+          name: String::from("DOUBLE AGENT"),
+          unlocked: false,
+          condition: |game_event, _progress| match game_event {
+            GameEvent::Swapped { total_swaps } => *total_swaps >= 100,
+            _ => false,
+          },
+        },
+      ],
+      progress: Progress::default(),
+      toasts: vec![],
+      toast_time: 0,
+      toast_map: None,
+    }
+  }
+}
+
+impl Achievements {
+  pub fn notify(&mut self, game_event: &GameEvent) {
+    if let GameEvent::BuglympicsFinished {
+      nation,
+      place: Some(0),
+      ..
+    } = game_event
+    {
+      self.progress.gold_nations.insert(nation.to_string());
+    }
+
+    for achievement in self.list.iter_mut() {
+      if !achievement.unlocked && (achievement.condition)(game_event, &self.progress) {
+        achievement.unlocked = true;
+        self
+          .toasts
+          .push(format!("ACHIEVEMENT UNLOCKED\n{}", achievement.name));
+      }
+    }
+  }
+
+  // Made when a scene loads, before its own tile maps, so scenes that find
+  // their maps as the last one added aren't thrown off by a toast
+  pub fn reserve_toast(&mut self, bus: &mut LentSysBus) {
+    TextBox::new(
+      String::from(""),
+      8.0,
+      224.0,
+      String::from("start_font_small"),
+      String::from("start_font_small"),
+      8,
+      Some(22),
+      Some(2),
+    )
+    .to_tilemap(bus);
+
+    let last_tm = bus.ppu.tile_maps.len() - 1;
+    bus.ppu.tile_maps[last_tm].order = 1;
+    self.toast_map = Some(last_tm);
+
+    // carry on with a toast from the last scene
+    if let Some(toast) = self.toasts.first() {
+      bus.ppu.tile_maps[last_tm].update_text(toast.to_string());
+    }
+  }
+
+  // Show queued toasts one at a time on top of the current scene
+  pub fn update_toast(&mut self, bus: &mut LentSysBus) {
+    if self.toasts.is_empty() {
+      return;
+    }
+
+    let map_idx = match self.toast_map {
+      Some(idx) if idx < bus.ppu.tile_maps.len() => idx,
+      _ => return,
+    };
+
+    if self.toast_time == 0 {
+      bus.ppu.tile_maps[map_idx].update_text(self.toasts[0].to_string());
+    }
+
+    self.toast_time += 1;
+
+    if self.toast_time > 120 {
+      self.toasts.remove(0);
+      self.toast_time = 0;
+      bus.ppu.tile_maps[map_idx].update_text(String::from(""));
+    }
+  }
+
+  pub fn read(&mut self, save: &SaveData) {
+    for achievement in self.list.iter_mut() {
+      achievement.unlocked = save.get_bool(&format!("achievement.{}", achievement.id), false);
+    }
+
+    if let Some(nations) = save.get_str("achievement.gold_nations") {
+      self.progress.gold_nations = nations
+        .split(',')
+        .filter(|nation| !nation.is_empty())
+        .map(|nation| nation.to_string())
+        .collect();
+    }
+  }
+
+  pub fn write(&self, save: &mut SaveData) {
+    for achievement in self.list.iter() {
+      save.set(&format!("achievement.{}", achievement.id), achievement.unlocked);
+    }
+
+    let mut nations: Vec<&String> = self.progress.gold_nations.iter().collect();
+    nations.sort();
+    save.set(
+      "achievement.gold_nations",
+      nations
+        .iter()
+        .map(|nation| nation.as_str())
+        .collect::<Vec<&str>>()
+        .join(","),
+    );
+  }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use lentsys::control::PadControl;
+
+use crate::game::save::SaveData;
+
+// keys held per pad button, the oldest is dropped past this
+pub const MAX_KEYS: usize = 3;
+// keys are saved comma separated, so the comma key is saved by this name
+pub const COMMA_NAME: &str = "Comma";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadButton {
+  Up,
+  Down,
+  Left,
+  Right,
+  Start,
+  A,
+  B,
+  X,
+  Y,
+}
+
+impl PadButton {
+  // matches the PadControl field, which the web build relies on
+  pub fn name(&self) -> &str {
+    match self {
+      PadButton::Up => "up",
+      PadButton::Down => "down",
+      PadButton::Left => "left",
+      PadButton::Right => "right",
+      PadButton::Start => "start",
+      PadButton::A => "a",
+      PadButton::B => "b",
+      PadButton::X => "x",
+      PadButton::Y => "y",
+    }
+  }
+
+  pub fn label(&self) -> &str {
+    match self {
+      PadButton::Up => "UP",
+      PadButton::Down => "DOWN",
+      PadButton::Left => "LEFT",
+      PadButton::Right => "RIGHT",
+      PadButton::Start => "CONFIRM",
+      PadButton::A => "JUMP",
+      PadButton::B => "SWAP",
+      PadButton::X => "FIRE",
+      PadButton::Y => "CANCEL",
+    }
+  }
+
+  pub fn press(&self, controller: &mut PadControl) {
+    match self {
+      PadButton::Up => controller.up = 255,
+      PadButton::Down => controller.down = 255,
+      PadButton::Left => controller.left = 255,
+      PadButton::Right => controller.right = 255,
+      PadButton::Start => controller.start = 255,
+      PadButton::A => controller.a = 255,
+      PadButton::B => controller.b = 255,
+      PadButton::X => controller.x = 255,
+      PadButton::Y => controller.y = 255,
+    }
+  }
+}
+
+pub struct KeyBinding {
+  pub button: PadButton,
+  pub keys: Vec<String>,
+}
+
+// Key names follow SDL's naming, e.g. "Left", "Return", "Q"
+pub struct KeyBindings {
+  pub list: Vec<KeyBinding>,
+}
+
+impl Default for KeyBindings {
+  fn default() -> Self {
+    let binding = |button, keys: &[&str]| KeyBinding {
+      button,
+      keys: keys.iter().map(|key| key.to_string()).collect(),
+    };
+
+    Self {
+      list: vec![
+        binding(PadButton::Up, &["Up"]),
+        binding(PadButton::Down, &["Down"]),
+        binding(PadButton::Left, &["Left"]),
+        binding(PadButton::Right, &["Right"]),
+        binding(PadButton::Start, &["Return"]),
+        binding(PadButton::A, &["Z"]),
+        binding(PadButton::B, &["Q"]),
+        binding(PadButton::X, &["A"]),
+        binding(PadButton::Y, &["Backspace"]),
+      ],
+    }
+  }
+}
+
+impl KeyBindings {
+  pub fn to_pad(&self, keys: &HashSet<String>, controller: &mut PadControl) {
+    for binding in self.list.iter() {
+      if binding.keys.iter().any(|key| keys.contains(key)) {
+        binding.button.press(controller);
+      }
+    }
+  }
+
+  // A key only drives one button, so binding it takes it from the others
+  pub fn bind(&mut self, idx: usize, key: &str) {
+    for binding in self.list.iter_mut() {
+      binding.keys.retain(|bound| bound != key);
+    }
+
+    let keys = &mut self.list[idx].keys;
+    keys.push(key.to_string());
+    if keys.len() > MAX_KEYS {
+      keys.remove(0);
+    }
+  }
+
+  pub fn clear(&mut self, idx: usize) {
+    self.list[idx].keys.clear();
+  }
+
+  pub fn read(save: &SaveData) -> KeyBindings {
+    let mut bindings = KeyBindings::default();
+    for binding in bindings.list.iter_mut() {
+      if let Some(keys) = save.get_str(&format!("keys.{}", binding.button.name())) {
+        binding.keys = keys
+          .split(',')
+          .filter(|key| !key.is_empty())
+          .take(MAX_KEYS)
+          .map(|key| match key {
+            COMMA_NAME => String::from(","),
+            _ => key.to_string(),
+          })
+          .collect();
+      }
+    }
+    bindings
+  }
+
+  pub fn write(&self, save: &mut SaveData) {
+    for binding in self.list.iter() {
+      let keys: Vec<&str> = binding
+        .keys
+        .iter()
+        .map(|key| match key.as_str() {
+          "," => COMMA_NAME,
+          key => key,
+        })
+        .collect();
+      save.set(&format!("keys.{}", binding.button.name()), keys.join(","));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keys(bindings: &KeyBindings, button: PadButton) -> Vec<String> {
+    bindings
+      .list
+      .iter()
+      .find(|binding| binding.button == button)
+      .map(|binding| binding.keys.clone())
+      .unwrap_or_default()
+  }
+
+  #[test]
+  fn write_then_read_keeps_every_binding() {
+    let mut bindings = KeyBindings::default();
+    bindings.bind(0, "W");
+    bindings.bind(5, "Space");
+    bindings.clear(8);
+
+    let mut save = SaveData::default();
+    bindings.write(&mut save);
+    let read = KeyBindings::read(&SaveData::parse(&save.serialize()));
+
+    for binding in bindings.list.iter() {
+      assert_eq!(keys(&read, binding.button), binding.keys);
+    }
+  }
+
+  #[test]
+  fn comma_key_survives_the_round_trip() {
+    let mut bindings = KeyBindings::default();
+    bindings.bind(7, ",");
+
+    let mut save = SaveData::default();
+    bindings.write(&mut save);
+    assert_eq!(save.get_str("keys.x"), Some("A,Comma"));
+
+    let read = KeyBindings::read(&SaveData::parse(&save.serialize()));
+    assert_eq!(keys(&read, PadButton::X), vec!["A", ","]);
+  }
+
+  #[test]
+  fn binding_a_key_takes_it_from_other_buttons() {
+    let mut bindings = KeyBindings::default();
+    bindings.bind(0, "Z");
+    assert_eq!(keys(&bindings, PadButton::Up), vec!["Up", "Z"]);
+    assert!(keys(&bindings, PadButton::A).is_empty());
+  }
+
+  #[test]
+  fn oldest_key_drops_past_the_limit() {
+    let mut bindings = KeyBindings::default();
+    for key in ["1", "2", "3"].iter() {
+      bindings.bind(0, key);
+    }
+    assert_eq!(keys(&bindings, PadButton::Up), vec!["1", "2", "3"]);
+  }
+}
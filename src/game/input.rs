@@ -1,7 +1,7 @@
 use lentsys::control::PadControl;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum InputCode {
   Up,
   Down,
@@ -10,6 +10,7 @@ pub enum InputCode {
   Jump,
   Fire,
   Confirm,
+  Cancel,
   Swap
 }
 
@@ -33,6 +34,10 @@ pub fn map_input(controller: PadControl) -> HashSet<InputCode>{
     inputs.insert(InputCode::Fire);
   }
 
+  if controller.y > 0 {
+    inputs.insert(InputCode::Cancel);
+  }
+
   if controller.up > 0 {
     inputs.insert(InputCode::Up);
   }
@@ -50,4 +55,172 @@ pub fn map_input(controller: PadControl) -> HashSet<InputCode>{
   }
 
   return inputs;
-}
\ No newline at end of file
+}
+
+// frames a menu direction is held before it starts repeating, and how often
+pub const REPEAT_DELAY: u32 = 20;
+pub const REPEAT_RATE: u32 = 6;
+
+// Held buttons with the previous frame in mind, so a press is seen once
+#[derive(Default)]
+pub struct InputState {
+  // frames each held code has been down, 1 on the frame it was pressed
+  pub held: HashMap<InputCode, u32>,
+  // codes let go this frame, with how long they had been held
+  pub released: HashMap<InputCode, u32>,
+  // how far each direction is pushed, 0.0 to 1.0
+  pub magnitude: HashMap<InputCode, f32>,
+}
+
+impl InputState {
+  pub fn update(&mut self, inputs: &HashSet<InputCode>, controller: &PadControl) {
+    let previous = std::mem::take(&mut self.held);
+
+    self.released = previous
+      .iter()
+      .filter(|(code, _)| !inputs.contains(code))
+      .map(|(code, frames)| (*code, *frames))
+      .collect();
+
+    self.held = inputs
+      .iter()
+      .map(|code| (*code, previous.get(code).unwrap_or(&0) + 1))
+      .collect();
+
+    // keys press fully, sticks fill in the range between
+    self.magnitude = vec![
+      (InputCode::Up, controller.up),
+      (InputCode::Down, controller.down),
+      (InputCode::Left, controller.left),
+      (InputCode::Right, controller.right),
+    ]
+    .into_iter()
+    .map(|(code, value)| (code, value as f32 / 255.0))
+    .collect();
+  }
+
+  pub fn pressed(&self, code: InputCode) -> bool {
+    self.held_for(code) == 1
+  }
+
+  pub fn held(&self, code: InputCode) -> bool {
+    self.held.contains_key(&code)
+  }
+
+  pub fn held_for(&self, code: InputCode) -> u32 {
+    *self.held.get(&code).unwrap_or(&0)
+  }
+
+  pub fn amount(&self, code: InputCode) -> f32 {
+    *self.magnitude.get(&code).unwrap_or(&0.0)
+  }
+
+  pub fn released(&self, code: InputCode) -> bool {
+    self.released.contains_key(&code)
+  }
+
+  pub fn released_after(&self, code: InputCode) -> Option<u32> {
+    self.released.get(&code).cloned()
+  }
+
+  // pressed, then again every REPEAT_RATE frames once held past REPEAT_DELAY
+  pub fn repeated(&self, code: InputCode) -> bool {
+    let frames = self.held_for(code);
+    frames == 1 || (frames > REPEAT_DELAY && (frames - REPEAT_DELAY) % REPEAT_RATE == 0)
+  }
+
+  // the same state with some codes ignored
+  pub fn without(&self, codes: &[InputCode]) -> InputState {
+    InputState {
+      held: self
+        .held
+        .iter()
+        .filter(|(code, _)| !codes.contains(code))
+        .map(|(code, frames)| (*code, *frames))
+        .collect(),
+      released: self
+        .released
+        .iter()
+        .filter(|(code, _)| !codes.contains(code))
+        .map(|(code, frames)| (*code, *frames))
+        .collect(),
+      magnitude: self
+        .magnitude
+        .iter()
+        .filter(|(code, _)| !codes.contains(code))
+        .map(|(code, amount)| (*code, *amount))
+        .collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn step(state: &mut InputState, codes: &[InputCode]) {
+    let inputs: HashSet<InputCode> = codes.iter().cloned().collect();
+    state.update(&inputs, &PadControl::new());
+  }
+
+  #[test]
+  fn press_is_seen_on_the_first_frame_only() {
+    let mut state = InputState::default();
+    step(&mut state, &[InputCode::Jump]);
+    assert!(state.pressed(InputCode::Jump));
+    assert!(state.held(InputCode::Jump));
+
+    step(&mut state, &[InputCode::Jump]);
+    assert!(!state.pressed(InputCode::Jump));
+    assert!(state.held(InputCode::Jump));
+    assert_eq!(state.held_for(InputCode::Jump), 2);
+  }
+
+  #[test]
+  fn release_reports_how_long_it_was_held() {
+    let mut state = InputState::default();
+    step(&mut state, &[InputCode::Fire]);
+    step(&mut state, &[InputCode::Fire]);
+    step(&mut state, &[InputCode::Fire]);
+    assert!(!state.released(InputCode::Fire));
+
+    step(&mut state, &[]);
+    assert!(state.released(InputCode::Fire));
+    assert_eq!(state.released_after(InputCode::Fire), Some(3));
+    assert!(!state.held(InputCode::Fire));
+
+    step(&mut state, &[]);
+    assert!(!state.released(InputCode::Fire));
+  }
+
+  #[test]
+  fn press_again_after_release() {
+    let mut state = InputState::default();
+    step(&mut state, &[InputCode::Confirm]);
+    step(&mut state, &[]);
+    step(&mut state, &[InputCode::Confirm]);
+    assert!(state.pressed(InputCode::Confirm));
+  }
+
+  #[test]
+  fn held_direction_repeats_after_the_delay() {
+    let mut state = InputState::default();
+    let mut repeats = vec![];
+    for frame in 1..=REPEAT_DELAY + REPEAT_RATE {
+      step(&mut state, &[InputCode::Down]);
+      if state.repeated(InputCode::Down) {
+        repeats.push(frame);
+      }
+    }
+    assert_eq!(repeats, vec![1, REPEAT_DELAY + REPEAT_RATE]);
+  }
+
+  #[test]
+  fn without_ignores_the_given_codes() {
+    let mut state = InputState::default();
+    step(&mut state, &[InputCode::Jump, InputCode::Fire]);
+    let state = state.without(&[InputCode::Jump]);
+    assert!(!state.pressed(InputCode::Jump));
+    assert!(state.pressed(InputCode::Fire));
+  }
+}
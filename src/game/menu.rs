@@ -1,17 +1,63 @@
-use std::collections::HashSet;
-
 use lentsys::lentsys::LentSysBus;
 use lentsys::ppu::sprite::Sprite;
-use lentsys::ui::text::TextBox;
+use lentsys::ui::text::{Text, TextBox};
 
 use crate::game::sounds;
-use crate::game::input::{InputCode};
+use crate::game::input::{InputCode, InputState};
+
+// A value changed with left and right next to a menu entry
+#[derive(Debug, Clone)]
+pub enum MenuValue {
+  Slider { value: u8, min: u8, max: u8 },
+  Toggle(bool),
+  Choice { index: usize, choices: Vec<String> },
+}
+
+impl MenuValue {
+  pub fn step(&mut self, step: i8) {
+    match self {
+      MenuValue::Slider { value, min, max } => {
+        *value = (*value as i16 + step as i16).max(*min as i16).min(*max as i16) as u8;
+      }
+      MenuValue::Toggle(on) => *on = !*on,
+      MenuValue::Choice { index, choices } => {
+        let len = choices.len() as i32;
+        if len == 0 {
+          return;
+        }
+        *index = ((*index as i32 + step as i32 + len) % len) as usize;
+      }
+    }
+  }
+
+  pub fn text(&self) -> String {
+    match self {
+      MenuValue::Slider { value, min, max } => format!(
+        "{}{}",
+        "|".repeat((*value - *min) as usize),
+        ".".repeat((*max - *value) as usize)
+      ),
+      MenuValue::Toggle(on) => String::from(if *on { "ON" } else { "OFF" }),
+      MenuValue::Choice { index, choices } => choices.get(*index).cloned().unwrap_or_default(),
+    }
+  }
+
+  // longest text any setting of this value can show
+  pub fn max_len(&self) -> usize {
+    match self {
+      MenuValue::Slider { min, max, .. } => (*max - *min) as usize,
+      MenuValue::Toggle(_) => 3,
+      MenuValue::Choice { choices, .. } => choices.iter().map(|choice| choice.len()).max().unwrap_or(0),
+    }
+  }
+}
 
 pub struct Menu {
   pub name: String,
   pub screen_x: u16,
   pub screen_y: u16,
   pub options: Vec<String>,
+  // one position per visible slot, in reading order
   pub option_positions: Vec<[u16; 2]>,
   pub current_selection: usize,
   pub confirmed: bool,
@@ -22,42 +68,158 @@ pub struct Menu {
   pub cursor_tile_id: usize,
   pub cursor_sprite_id: usize,
   pub cursor_offset: [i16; 2],
-  pub input_time: u16,
-  pub input_threshold: u16,
+  // grid layout, 1 for a plain list
+  pub columns: usize,
+  // rows shown at once, 0 shows every row
+  pub visible_rows: usize,
+  pub scroll: usize,
+  // per option, empty when none are disabled
+  pub disabled: Vec<bool>,
+  pub values: Vec<Option<MenuValue>>,
+  // option text is padded to this before its value
+  pub label_width: usize,
+  // characters per slot, 0 fits the longest label
+  pub text_width: usize,
+  // the option whose value changed this frame
+  pub changed: Option<usize>,
+  pub slot_maps: Vec<usize>,
+  pub palette_id: usize,
+  pub disabled_palette_id: usize,
+}
+
+impl Default for Menu {
+  fn default() -> Self {
+    Self {
+      name: String::from(""),
+      screen_x: 0,
+      screen_y: 0,
+      options: vec![],
+      option_positions: vec![],
+      current_selection: 0,
+      confirmed: false,
+      text_tile_set_name: String::from("start_font_small"),
+      palette_name: String::from("start_font_small"),
+      font_size: 8,
+      cursor_tile_set_id: 2,
+      cursor_tile_id: 9,
+      cursor_sprite_id: 0,
+      cursor_offset: [-12, 0],
+      columns: 1,
+      visible_rows: 0,
+      scroll: 0,
+      disabled: vec![],
+      values: vec![],
+      label_width: 0,
+      text_width: 0,
+      changed: None,
+      slot_maps: vec![],
+      palette_id: 0,
+      disabled_palette_id: 0,
+    }
+  }
 }
 
 impl Menu {
+  // positions for a grid of slots, left to right then top to bottom
+  pub fn grid_positions(origin: [u16; 2], spacing: [u16; 2], columns: usize, slots: usize) -> Vec<[u16; 2]> {
+    (0..slots)
+      .map(|slot| {
+        [
+          origin[0] + spacing[0] * (slot % columns) as u16,
+          origin[1] + spacing[1] * (slot / columns) as u16,
+        ]
+      })
+      .collect()
+  }
+
   pub fn load(&mut self, bus: &mut LentSysBus) {
-    self.input_time = 0;
-    self.input_threshold = 3;
-    for (idx, opt) in self.options.iter().enumerate() {
+    // without scrolling every option needs a slot of its own
+    assert!(
+      self.visible_rows > 0 || self.options.len() <= self.option_positions.len(),
+      "{} menu has {} options for {} positions",
+      self.name,
+      self.options.len(),
+      self.option_positions.len()
+    );
+
+    self.slot_maps = vec![];
+    self.keep_in_view();
+
+    // slots are sized for the longest label so they can be reused
+    let width = (0..self.options.len())
+      .map(|idx| match self.value(idx) {
+        Some(value) => self.label_width.max(self.options[idx].len()) + value.max_len(),
+        None => self.options[idx].lines().map(|line| line.len()).max().unwrap_or(0),
+      })
+      .max()
+      .unwrap_or(1)
+      .max(self.text_width);
+    let height = self
+      .options
+      .iter()
+      .map(|option| option.lines().count())
+      .max()
+      .unwrap_or(1)
+      .max(1);
+
+    for (slot, position) in self.option_positions.iter().enumerate() {
       TextBox::new(
-        String::from(opt),
-        self.option_positions[idx][0] as f32,
-        self.option_positions[idx][1] as f32,
+        String::from(""),
+        position[0] as f32,
+        position[1] as f32,
         self.text_tile_set_name.to_string(),
         self.palette_name.to_string(),
         self.font_size,
-        None,
-        None,
+        Some(width as _),
+        Some(height as _),
       )
       .to_tilemap(bus);
 
       // TODO: actually handle order
       let last_tm = bus.ppu.tile_maps.len() - 1;
       bus.ppu.tile_maps[last_tm].order = 1;
+      self.slot_maps.push(last_tm);
+
+      if slot == 0 {
+        self.palette_id = bus.ppu.tile_maps[last_tm].palette_id;
+      }
     }
 
+    // disabled entries use a dimmed copy of the text palette, made once
+    // and found again by later loads
+    if !self.slot_maps.is_empty() {
+      let mut dimmed = bus.ppu.palettes[self.palette_id].clone();
+      for color in dimmed.data.iter_mut() {
+        for channel in color.iter_mut().take(3) {
+          *channel /= 2;
+        }
+      }
+
+      let existing = bus
+        .ppu
+        .palettes
+        .iter()
+        .position(|palette| palette.data == dimmed.data);
+      self.disabled_palette_id = match existing {
+        Some(idx) => idx,
+        None => {
+          bus.ppu.palettes.push(dimmed);
+          bus.ppu.palettes.len() - 1
+        }
+      };
+    }
+
+    self.refresh(bus);
+
+    let cursor_pos = self.position(self.current_selection);
     bus.ppu.sprites.push(Sprite {
       entity_id: 0,
       tile_set_id: self.cursor_tile_set_id,
       tile_id: self.cursor_tile_id as u16,
-      palette_id: (bus.ppu.palettes.len() - 1) as u16,
+      palette_id: self.palette_id as u16,
       lines_drawn: 0,
-      scene_x: (self.option_positions[self.current_selection][0] as i16 + self.cursor_offset[0])
-        as u16,
-      scene_y: (self.option_positions[self.current_selection][1] as i16 + self.cursor_offset[1])
-        as u16,
+      scene_x: (cursor_pos[0] as i16 + self.cursor_offset[0]) as u16,
+      scene_y: (cursor_pos[1] as i16 + self.cursor_offset[1]) as u16,
       reverse_x: false,
       reverse_y: false,
       width: self.font_size,
@@ -71,52 +233,145 @@ impl Menu {
     sounds::prepare_effects(bus);
   }
 
-  pub fn update_cursor(&mut self, keys: &HashSet<InputCode>, bus: &mut LentSysBus) {
-    let mut any_input = false;
-    if keys.contains(&InputCode::Down) || keys.contains(&InputCode::Right) {
-      self.input_time += 1;
-      if self.input_time > self.input_threshold && self.options.len() - 1 > 0 {
-        self.current_selection += 1;
-        sounds::play_effect(bus, sounds::SFX::Switch, 800);
-        self.input_time = 0;
-      }
-      
-      any_input = true;
+  pub fn is_disabled(&self, idx: usize) -> bool {
+    *self.disabled.get(idx).unwrap_or(&false)
+  }
+
+  pub fn value(&self, idx: usize) -> Option<&MenuValue> {
+    self.values.get(idx).and_then(|value| value.as_ref())
+  }
+
+  pub fn label(&self, idx: usize) -> String {
+    match self.value(idx) {
+      Some(value) => format!(
+        "{:<width$}{}",
+        self.options[idx],
+        value.text(),
+        width = self.label_width
+      ),
+      None => self.options[idx].to_string(),
     }
+  }
 
-    if keys.contains(&InputCode::Up) || keys.contains(&InputCode::Left) {
-      self.input_time += 1;
-      if self.input_time > self.input_threshold && self.options.len() - 1 > 0 {
-        if self.current_selection == 0 {
-          self.current_selection = self.options.len() - 1;
+  fn first_visible(&self) -> usize {
+    self.scroll * self.columns
+  }
+
+  pub fn position(&self, idx: usize) -> [u16; 2] {
+    self.option_positions[idx - self.first_visible()]
+  }
+
+  // Redraw each slot's text and palette, after labels, values or the
+  // disabled entries change
+  pub fn refresh(&self, bus: &mut LentSysBus) {
+    for (slot, map_idx) in self.slot_maps.iter().enumerate() {
+      let idx = self.first_visible() + slot;
+      let (text, palette_id) = if idx < self.options.len() {
+        let palette_id = if self.is_disabled(idx) {
+          self.disabled_palette_id
         } else {
-          self.current_selection -= 1;
+          self.palette_id
+        };
+        (self.label(idx), palette_id)
+      } else {
+        (String::from(""), self.palette_id)
+      };
+
+      bus.ppu.tile_maps[*map_idx].update_text(text);
+      bus.ppu.tile_maps[*map_idx].palette_id = palette_id;
+    }
+  }
+
+  // scroll so the selected row is on screen, true if it moved
+  fn keep_in_view(&mut self) -> bool {
+    if self.visible_rows == 0 {
+      return false;
+    }
+
+    let row = self.current_selection / self.columns;
+    let scroll = if row < self.scroll {
+      row
+    } else if row >= self.scroll + self.visible_rows {
+      row + 1 - self.visible_rows
+    } else {
+      self.scroll
+    };
+
+    let moved = scroll != self.scroll;
+    self.scroll = scroll;
+    moved
+  }
+
+  fn move_selection(&mut self, step: i32) {
+    let len = self.options.len() as i32;
+    self.current_selection = ((self.current_selection as i32 + step + len) % len) as usize;
+  }
+
+  // up and down stay in the column, wrapping past a short last row
+  fn move_row(&mut self, step: i32) {
+    let columns = self.columns;
+    let column = self.current_selection % columns;
+    let rows = ((self.options.len() - column + columns - 1) / columns) as i32;
+    let row = (self.current_selection / columns) as i32;
+    self.current_selection = ((row + step + rows) % rows) as usize * columns + column;
+  }
+
+  pub fn update_cursor(&mut self, input: &InputState, bus: &mut LentSysBus) {
+    self.changed = None;
+    if self.options.is_empty() {
+      return;
+    }
+
+    let previous_selection = self.current_selection;
+    let right = input.repeated(InputCode::Right);
+    let left = input.repeated(InputCode::Left);
+
+    if input.repeated(InputCode::Down) {
+      self.move_row(1);
+    }
+
+    if input.repeated(InputCode::Up) {
+      self.move_row(-1);
+    }
+
+    // left and right change a value, otherwise they move along
+    if right || left {
+      let step = if right { 1 } else { -1 };
+      let selection = self.current_selection;
+      if self.value(selection).is_some() && !self.is_disabled(selection) {
+        if let Some(Some(value)) = self.values.get_mut(selection) {
+          value.step(step);
         }
-        sounds::play_effect(bus, sounds::SFX::Switch, 800);
-        self.input_time = 0;
+        self.changed = Some(selection);
+        sounds::play_effect(bus, sounds::SFX::Switch);
+      } else {
+        self.move_selection(step as i32);
       }
-      any_input = true;
     }
 
-    if keys.contains(&InputCode::Fire) || keys.contains(&InputCode::Confirm) {
-      self.confirmed = true;
-      any_input = true;
-      // sound
-      sounds::play_effect(bus, sounds::SFX::Select, 800);
+    if self.current_selection != previous_selection {
+      sounds::play_effect(bus, sounds::SFX::Switch);
     }
 
-    if self.current_selection >= self.options.len() {
-      self.current_selection = 0;
+    if input.pressed(InputCode::Fire) || input.pressed(InputCode::Confirm) {
+      if self.is_disabled(self.current_selection) {
+        sounds::play_effect(bus, sounds::SFX::Denied);
+      } else {
+        self.confirmed = true;
+        // sound
+        sounds::play_effect(bus, sounds::SFX::Select);
+      }
     }
 
-    if !any_input {
-      self.input_time = 0;
+    if self.keep_in_view() || self.changed.is_some() {
+      self.refresh(bus);
     }
 
+    let cursor_pos = self.position(self.current_selection);
     bus.ppu.sprites[self.cursor_sprite_id].scene_x =
-      (self.option_positions[self.current_selection][0] as i16 + self.cursor_offset[0]) as u16;
+      (cursor_pos[0] as i16 + self.cursor_offset[0]) as u16;
     bus.ppu.sprites[self.cursor_sprite_id].scene_y =
-      (self.option_positions[self.current_selection][1] as i16 + self.cursor_offset[1]) as u16;
+      (cursor_pos[1] as i16 + self.cursor_offset[1]) as u16;
   }
 }
 
@@ -139,8 +394,7 @@ mod test {
       cursor_tile_id: 16,
       cursor_sprite_id: 0,
       cursor_offset: [-16, 0],
-      input_time: 0,
-      input_threshold: 30,
+      ..Menu::default()
     };
   }
 }
@@ -3,4 +3,11 @@ pub mod sounds;
 pub mod player;
 pub mod state;
 pub mod menu;
-pub mod input;
\ No newline at end of file
+pub mod input;
+pub mod tricks;
+pub mod save;
+pub mod stats;
+pub mod achievements;
+pub mod settings;
+pub mod bindings;
+pub mod songs;
\ No newline at end of file
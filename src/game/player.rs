@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-
 use lentsys::ecs::components::collision::BoxCollider;
 use lentsys::ecs::components::collision::Collide;
 use lentsys::ecs::components::collision::Ray;
@@ -11,7 +9,9 @@ use lentsys::lentsys::LentSysBus;
 
 use crate::game::state::{GameMode, WorldState, Target};
 use crate::game::sounds;
-use crate::game::input::{InputCode};
+use crate::game::stats::Stats;
+use crate::game::tricks::Tricks;
+use crate::game::input::{InputCode, InputState};
 
 #[derive(Debug)]
 pub enum PlayerState {
@@ -60,7 +60,14 @@ impl Default for Projectile {
 }
 
 impl Projectile {
-  fn update(&mut self, bus: &mut LentSysBus, world: &WorldState, targets: &mut Vec<Target>) {
+  fn update(
+    &mut self,
+    bus: &mut LentSysBus,
+    world: &WorldState,
+    targets: &mut Vec<Target>,
+    stats: &mut Stats,
+  ) {
+    let mut hit_target = false;
     if self.distance_traveled > self.max_distance {
       self.expired = true;
       bus.ppu.sprites[self.anim.sprite_id].hide = true;
@@ -79,16 +86,28 @@ impl Projectile {
       let (_mc_idx, mut hit) = get_nearest_map_collision(&mut sensor, bus, world);
 
       for tgt in targets.iter_mut() {
+        // already down, shots pass over them
+        if tgt.hit {
+          continue;
+        }
+
         sensor.check_box_collision([0.0, 0.0], &tgt.collider, &tgt.transform);
         //println!("{:?}", sensor.collided);
         if sensor.collided.len() > 0 {
           tgt.hit = true;
           hit = true;
+          hit_target = true;
           bus.ppu.sprites[tgt.anim.sprite_id].hide = true;
           //println!("TARGET HIT!");
+          // a shot stops at the first target it hits
+          break;
         }
       }
 
+      if hit_target {
+        stats.shots_hit += 1;
+      }
+
       if hit {
         self.expired = true;
         bus.ppu.sprites[self.anim.sprite_id].hide = true;
@@ -123,7 +142,13 @@ impl Default for Launcher {
 }
 
 impl Launcher {
-  fn fire(&mut self, start_pos: [f32; 2], direction: [f32; 2], bus: &mut LentSysBus) {
+  fn fire(
+    &mut self,
+    start_pos: [f32; 2],
+    direction: [f32; 2],
+    bus: &mut LentSysBus,
+    stats: &mut Stats,
+  ) {
     self.projectiles = self.projectiles.drain(..).filter(|p| !p.expired).collect();
     if self.ammo < 1 {
       return;
@@ -157,13 +182,14 @@ impl Launcher {
       //println!("{} {}", bus.ppu.sprites[proj.anim.sprite_id].tile_id, self.projectile_tile);
 
       // sound
-      sounds::play_effect(bus, sounds::SFX::Fire, 8000);
+      sounds::play_effect(bus, sounds::SFX::Fire);
 
       proj.anim.sprite_id = bus.ppu.sprites.len() - 1;
       self.projectiles.push(proj);
 
       self.cooldown = 0;
       self.ammo -= 1;
+      stats.shots_fired += 1;
     }
   }
 }
@@ -175,19 +201,43 @@ pub struct Player {
   pub vel_x: f32,
   pub vel_y: f32,
   pub jumping: bool,
+  pub jump_buffer: u32,
+  pub jump_buffered: u32,
+  pub coyote_time: u32,
+  pub air_frames: u32,
   pub running: bool,
   pub grounded: bool,
+  pub stable: bool,
   pub blocked: [bool; 2],
   pub finished: bool,
+  pub jumps: u32,
   pub player_state: PlayerState,
-  pub jump_force: f32,
+  pub min_jump_height: f32,
+  pub max_jump_height: f32,
+  pub jump_release_speed: f32,
+  pub jump_start_y: f32,
   pub slope: f32,
   pub slope_accel: f32,
+  pub surface_angle: f32,
+  pub step_height: f32,
+  pub ledge_margin: f32,
   pub accel_rate: f32,
   pub decel_rate: f32,
   pub walk_speed: f32,
   pub run_speed: f32,
   pub air_control: f32,
+  pub tucking: bool,
+  pub tuck_decel_rate: f32,
+  pub tuck_slope_accel: f32,
+  pub tuck_steering: f32,
+  pub tuck_speed: f32,
+  pub fast_fall_gravity: f32,
+  pub fast_fall_speed: f32,
+  pub safe_pos: [f32; 2],
+  pub respawned: bool,
+  pub invulnerable: u32,
+  pub respawn_invulnerability: u32,
+  pub tricks: Tricks,
   pub launcher: Launcher,
   pub transform: Transform,
   pub collider: BoxCollider,
@@ -212,16 +262,40 @@ impl Player {
       decel_rate: 0.9,
       walk_speed: 3.0,
       run_speed: 7.0,
-      jump_force: 10.0,
+      min_jump_height: 24.0,
+      max_jump_height: 100.0,
+      jump_release_speed: 3.0,
+      jump_start_y: 0.0,
       air_control: 0.75,
+      tucking: false,
+      tuck_decel_rate: 0.95,
+      tuck_slope_accel: 1.5,
+      tuck_steering: 0.3,
+      tuck_speed: 9.0,
+      fast_fall_gravity: 2.0,
+      fast_fall_speed: 12.0,
+      safe_pos: [start_pos[0] as f32, start_pos[1] as f32],
+      respawned: false,
+      invulnerable: 0,
+      respawn_invulnerability: 90,
       slope: 0.0,
       slope_accel: 1.0,
+      surface_angle: 0.0,
+      step_height: 12.0,
+      ledge_margin: 6.0,
       jumping: false,
+      jump_buffer: 6,
+      jump_buffered: 0,
+      coyote_time: 6,
+      air_frames: 0,
       running: false,
       grounded: false,
+      stable: false,
       blocked: [false, false],
       finished: false,
+      jumps: 0,
       player_state: PlayerState::Jumping,
+      tricks: Tricks::default(),
       launcher: Launcher {
         ..Launcher::default()
       },
@@ -254,6 +328,7 @@ impl Player {
   }
 
   pub fn init(&mut self, bus: &mut LentSysBus) {
+    self.drop_in();
     self
       .anim
       .add_to_sprites(&mut bus.ppu.sprites, &self.transform);
@@ -262,11 +337,12 @@ impl Player {
   pub fn update(
     &mut self, 
     bus: &mut LentSysBus, 
-    keys: &HashSet<InputCode>, 
+    input: &InputState, 
     game_mode: &GameMode,
     world: &WorldState,
     targets: &mut Vec<Target>,
     finish_line: [u16; 2],
+    stats: &mut Stats,
   ) {
     /*
       Roughly, the order should be
@@ -275,8 +351,34 @@ impl Player {
       - Collisions
       - Animation
     */
+    // Buglympics - tuck on the way downhill
+    self.tucking = match game_mode {
+      GameMode::Buglympics => {
+        input.held(InputCode::Down) && self.grounded && self.slope_accel > 1.0
+      }
+      GameMode::Spyder => false,
+    };
+
+    // Buglympics - hold up and steer to flip, forward or back
+    let flipping = match game_mode {
+      GameMode::Buglympics => input.held(InputCode::Up) && !self.grounded,
+      GameMode::Spyder => false,
+    };
+
+    let mut decel_rate = self.decel_rate;
+    let mut slope_accel = self.slope_accel;
+    let mut steering = 1.0;
+    let mut max_speed = self.run_speed;
+
+    if self.tucking {
+      decel_rate = self.tuck_decel_rate;
+      slope_accel = 1.0 + (self.slope_accel - 1.0) * self.tuck_slope_accel;
+      steering = self.tuck_steering;
+      max_speed = self.tuck_speed;
+    }
+
     //println!("{} {}", self.slope_accel,  ((self.slope_accel - 0.75) / 0.75 * 0.09));
-    self.vel_x *= self.decel_rate + ((self.slope_accel - 0.75) / 0.75 * 0.09);
+    self.vel_x *= decel_rate + ((slope_accel - 0.75) / 0.75 * 0.09);
     let mut move_speed = self.walk_speed;
     self.anim.rate = 6;
 
@@ -284,7 +386,7 @@ impl Player {
       self.launcher.cooldown += 1;
     }
 
-    if keys.contains(&InputCode::Fire) {
+    if input.held(InputCode::Fire) {
       match game_mode {
         GameMode::Buglympics => {
           move_speed = self.run_speed;
@@ -300,61 +402,96 @@ impl Player {
             [self.transform.scene_x, self.transform.scene_y],
             [direction, 0.0],
             bus,
+            stats,
           );
         }
       }
     }
 
+    // a press counts for a few frames before landing, and a jump is still
+    // allowed for a few frames after running off a ledge
+    if input.pressed(InputCode::Jump) {
+      self.jump_buffered = self.jump_buffer;
+    } else if self.jump_buffered > 0 {
+      self.jump_buffered -= 1;
+    }
+
+    let can_jump = match &self.player_state {
+      PlayerState::Jumping => !self.jumping && self.air_frames <= self.coyote_time,
+      _ => true,
+    };
+
     match &self.player_state {
       PlayerState::Jumping => {
-        if keys.contains(&InputCode::Right) {
-          self.vel_x += move_speed * self.accel_rate * self.air_control;
+        if input.held(InputCode::Right) && !flipping {
+          self.vel_x += move_speed * self.accel_rate * self.air_control * input.amount(InputCode::Right);
           bus.ppu.sprites[self.anim.sprite_id].reverse_x = false;
         }
 
-        if keys.contains(&InputCode::Left) {
-          self.vel_x += -move_speed * self.accel_rate * self.air_control;
+        if input.held(InputCode::Left) && !flipping {
+          self.vel_x += -move_speed * self.accel_rate * self.air_control * input.amount(InputCode::Left);
           bus.ppu.sprites[self.anim.sprite_id].reverse_x = true;
         }
 
-        self.vel_y = if self.vel_y >= 10.0 {
-          10.0
+        // letting go of jump early cuts the rise short, past the minimum height
+        let rise = self.jump_start_y - self.transform.scene_y;
+        if self.jumping && !input.held(InputCode::Jump) && rise >= self.min_jump_height {
+          self.vel_y = self.vel_y.max(-self.jump_release_speed);
+        }
+
+        // fast fall
+        let (gravity, max_fall) = if input.held(InputCode::Down) {
+          (world.gravity * self.fast_fall_gravity, self.fast_fall_speed)
+        } else {
+          (world.gravity, 10.0)
+        };
+
+        self.vel_y = if self.vel_y >= max_fall {
+          max_fall
         } else {
-          self.vel_y + world.gravity
+          self.vel_y + gravity
         };
       }
       PlayerState::Standing => {
         self.vel_y *= 0.0;
 
-        if keys.contains(&InputCode::Right) {
+        if input.held(InputCode::Right) {
           self.player_state = PlayerState::Walking;
-          self.vel_x += move_speed * self.slope_accel * self.accel_rate;
+          self.vel_x += move_speed * slope_accel * self.accel_rate * steering
+            * input.amount(InputCode::Right);
           bus.ppu.sprites[self.anim.sprite_id].reverse_x = false;
         }
-        if keys.contains(&InputCode::Left) {
+        if input.held(InputCode::Left) {
           self.player_state = PlayerState::Walking;
-          self.vel_x += -move_speed * self.slope_accel * self.accel_rate;
+          self.vel_x += -move_speed * slope_accel * self.accel_rate * steering
+            * input.amount(InputCode::Left);
           bus.ppu.sprites[self.anim.sprite_id].reverse_x = true;
         }
+      }
+      _ => self.player_state = PlayerState::Standing,
+    }
 
-        if keys.contains(&InputCode::Jump) {
-          self.player_state = PlayerState::Jumping;
-          self.vel_y = -self.jump_force;
-          match &game_mode {
-            crate::game::state::GameMode::Buglympics => {
-              sounds::play_effect(bus, sounds::SFX::JumpA, 800);
-            }
-            crate::game::state::GameMode::Spyder => {
-              sounds::play_effect(bus, sounds::SFX::JumpB, 800);
-            }
-          }
+    if self.jump_buffered > 0 && can_jump {
+      self.player_state = PlayerState::Jumping;
+      // launch fast enough to reach the max height if jump is held
+      self.vel_y = -(2.0 * world.gravity * self.max_jump_height).sqrt();
+      self.jump_start_y = self.transform.scene_y;
+      self.jumping = true;
+      self.jump_buffered = 0;
+      self.jumps += 1;
+      stats.jumps += 1;
+      match &game_mode {
+        crate::game::state::GameMode::Buglympics => {
+          sounds::play_effect(bus, sounds::SFX::JumpA);
+        }
+        crate::game::state::GameMode::Spyder => {
+          sounds::play_effect(bus, sounds::SFX::JumpB);
         }
       }
-      _ => self.player_state = PlayerState::Standing,
     }
 
-    self.vel_x = if self.vel_x.abs() >= self.run_speed {
-      self.run_speed * self.vel_x.signum()
+    self.vel_x = if self.vel_x.abs() >= max_speed {
+      max_speed * self.vel_x.signum()
     } else {
       self.vel_x
     };
@@ -369,6 +506,7 @@ impl Player {
     }
 
     // COLLISION
+    self.check_ceiling_collision(bus, &world);
     self.check_side_collision(bus, &world);
     self.grounded = false;
     self.check_ground_collision(bus, &world);
@@ -386,22 +524,71 @@ impl Player {
       _ => {}
     }
 
-    if !self.grounded {
+    if self.grounded {
+      self.jumping = false;
+      self.air_frames = 0;
+    } else {
       self.slope = 0.0;
       self.player_state = PlayerState::Jumping;
+      self.air_frames += 1;
+    }
+
+    // Buglympics - distance skied
+    match game_mode {
+      GameMode::Buglympics if self.grounded => {
+        stats.distance += self.vel_x.abs();
+      }
+      _ => {}
+    }
+
+    // AIRTIME / TRICKS
+    if !self.grounded {
+      if !self.tricks.airborne {
+        self.tricks.take_off(self.transform.scene_y);
+      }
+
+      // toward the way the skis are heading is a front flip
+      let spin = if flipping {
+        let heading = if self.vel_x < 0.0 { -1.0 } else { 1.0 };
+        (input.amount(InputCode::Right) - input.amount(InputCode::Left)) * heading
+      } else {
+        0.0
+      };
+      self.tricks.update_air(self.transform.scene_y, spin);
+      stats.airtime += 1;
+      bus.ppu.sprites[self.anim.sprite_id].reverse_y = self.tricks.tilt().abs() > 90.0;
+    } else if self.tricks.airborne {
+      bus.ppu.sprites[self.anim.sprite_id].reverse_y = false;
+      match game_mode {
+        GameMode::Buglympics => {
+          // wiped out, lose most of the speed
+          if self.tricks.land(self.surface_angle).is_none() {
+            self.vel_x *= 0.25;
+          }
+        }
+        GameMode::Spyder => {
+          self.tricks.airborne = false;
+        }
+      }
     }
 
     // PROJECTILES
     for proj in self.launcher.projectiles.iter_mut() {
-      proj.update(bus, world, targets);
+      proj.update(bus, world, targets, stats);
     }
 
     match &self.player_state {
+      PlayerState::Walking | PlayerState::Standing if self.tucking => {
+        self.anim.tile_range = [7, 7];
+        self.anim.jump_to(7, &mut bus.ppu.sprites);
+        sounds::play_effect(bus, sounds::SFX::Ski);
+        self.player_state = PlayerState::Standing;
+      }
       PlayerState::Walking => {
         if self.slope_accel > 1.0 {
           self.anim.tile_range = [5, 5];
           self.anim.jump_to(5, &mut bus.ppu.sprites);
-          sounds::play_effect(bus, sounds::SFX::Ski, 800);
+          sounds::play_effect(bus, sounds::SFX::Ski);
         } else {
           self.anim.tile_range = [1, 5];
           self.anim.advance_tile(&mut bus.ppu.sprites);
@@ -423,63 +610,194 @@ impl Player {
       }
     }
 
-    if self.transform.scene_y > 205.0 * 16.0 {
-      self.transform.scene_x = 100.0;
-      self.transform.scene_y = 100.0;
+    // RESPAWN
+    self.respawned = false;
+
+    if self.invulnerable > 0 {
+      self.invulnerable -= 1;
+      // flicker while invulnerable
+      bus.ppu.sprites[self.anim.sprite_id].hide =
+        self.invulnerable > 0 && (self.invulnerable / 4) % 2 == 1;
+    }
+
+    for checkpoint in world.checkpoints.iter() {
+      if (self.transform.scene_x - checkpoint[0]).abs() < 16.0
+        && (self.transform.scene_y - checkpoint[1]).abs() < 48.0
+      {
+        self.safe_pos = *checkpoint;
+      }
+    }
+
+    if self.stable && self.invulnerable == 0 {
+      self.safe_pos = [self.transform.scene_x, self.transform.scene_y];
+    }
+
+    if self.transform.scene_y > world.fall_limit {
+      self.respawn();
+      stats.falls += 1;
     }
 
     bus.ppu.sprites[self.anim.sprite_id].scene_x = self.transform.scene_x as u16;
     bus.ppu.sprites[self.anim.sprite_id].scene_y = self.transform.scene_y as u16;
   }
 
+  // Return to the last safe ground or checkpoint. Only a fall outside of the
+  // invulnerability window counts as `respawned`, so penalties don't stack.
+  pub fn respawn(&mut self) {
+    self.transform.scene_x = self.safe_pos[0];
+    self.transform.scene_y = self.safe_pos[1];
+    self.vel_x = 0.0;
+    self.vel_y = 0.0;
+    self.player_state = PlayerState::Jumping;
+    self.tricks.airborne = false;
+    self.drop_in();
+    self.respawned = self.invulnerable == 0;
+    self.invulnerable = self.respawn_invulnerability;
+  }
+
+  // Placed in the air rather than walked off a ledge, so there's no late
+  // jump to take and no jump in progress
+  fn drop_in(&mut self) {
+    self.jumping = false;
+    self.jump_buffered = 0;
+    self.air_frames = self.coyote_time + 1;
+  }
+
   fn check_ground_collision(&mut self, bus: &mut LentSysBus, world: &WorldState) {
+    self.stable = false;
+
     // if jumping, early exit;
     if self.vel_y < 0.0 {
       return;
     }
 
-    // cast 8 pixels below
-    let mut sensors = vec![Ray::new(
-      [
-        self.transform.scene_x + (self.collider.left + self.collider.right) * 0.5,
-        self.transform.scene_y + self.collider.bottom,
-      ],
-      [0.0, 1.0],
-      8.0,
-      8,
-    )];
+    // one sensor under each foot, set in from the sides by the ledge margin
+    let feet = [
+      self.collider.left + self.ledge_margin,
+      self.collider.right - self.ledge_margin,
+    ];
+
+    // (correction, slope, slope_accel, surface_angle) of the highest surface found
+    let mut ground: Option<(f32, f32, f32, f32)> = None;
+    let mut feet_down = 0;
+
+    for foot in feet.iter() {
+      // cast 8 pixels below
+      let mut sensor = Ray::new(
+        [
+          self.transform.scene_x + foot,
+          self.transform.scene_y + self.collider.bottom,
+        ],
+        [0.0, 1.0],
+        8.0,
+        8,
+      );
 
+      if let Some(probe) = self.probe_ground(&mut sensor, bus, world) {
+        feet_down += 1;
+        // the higher surface needs the smaller (more upward) correction
+        ground = match ground {
+          Some(found) if found.0 <= probe.0 => Some(found),
+          _ => Some(probe),
+        };
+      }
+    }
+
+    if let Some((correction, slope, slope_accel, surface_angle)) = ground {
+      self.grounded = true;
+      self.stable = feet_down == feet.len();
+      self.slope = slope;
+      self.slope_accel = slope_accel;
+      self.surface_angle = surface_angle;
+      self.transform.translate(0.0, correction);
+    }
+  }
+
+  fn probe_ground(
+    &mut self,
+    sensor: &mut Ray,
+    bus: &LentSysBus,
+    world: &WorldState,
+  ) -> Option<(f32, f32, f32, f32)> {
     // find tile_map collisions
-    let (sensor_idx, mc_idx, hit) = self.get_nearest_map_collision(&mut sensors, bus, world);
-
-    if hit {
-      self.grounded = hit;
-      let hit_mc = &sensors[sensor_idx].map_collided[mc_idx];
-      self.slope = self.find_surface_height(hit_mc.tile_id, hit_mc.point[0], world);
-      self.slope_accel = self.find_surface_angle(hit_mc.tile_id, hit_mc.point[0], world);
-
-      //if it is a solid tile, check above
-      if self.slope == 16.0 {
-        let tile_above = hit_mc.map_loc_id
-          - bus.ppu.tile_maps[world.collision_set.tile_set_id].columns as usize;
-        let tile_type_above =
-          bus.ppu.tile_maps[world.collision_set.tile_set_id].data[tile_above];
-        self.slope += self.find_surface_height(tile_type_above as usize, hit_mc.point[0], world);
-        self.slope_accel = self.find_surface_angle(hit_mc.tile_id, hit_mc.point[0], world);
+    let (mc_idx, hit) = get_nearest_map_collision(sensor, bus, world);
+
+    if !hit {
+      return None;
+    }
+
+    let hit_mc = &sensor.map_collided[mc_idx];
+    let mut slope = self.find_surface_height(hit_mc.tile_id, hit_mc.point[0], world);
+    let mut slope_accel = self.find_surface_angle(hit_mc.tile_id, hit_mc.point[0], world);
+    let mut surface_angle = self.find_surface_degrees(hit_mc.tile_id, world);
+
+    //if it is a solid tile, check above
+    if slope == 16.0 {
+      let tile_above = hit_mc.map_loc_id
+        - bus.ppu.tile_maps[world.collision_set.tile_set_id].columns as usize;
+      let tile_type_above =
+        bus.ppu.tile_maps[world.collision_set.tile_set_id].data[tile_above];
+      slope += self.find_surface_height(tile_type_above as usize, hit_mc.point[0], world);
+      slope_accel = self.find_surface_angle(hit_mc.tile_id, hit_mc.point[0], world);
+      surface_angle = self.find_surface_degrees(tile_type_above as usize, world);
+    }
+
+    // if hit detected early (up to 8 pixels), correct
+    let err = hit_mc.point[1] - sensor.origin[1];
+
+    Some((hit_mc.overlap[1] + err + (16.0 - slope), slope, slope_accel, surface_angle))
+  }
+
+  fn check_ceiling_collision(&mut self, bus: &LentSysBus, world: &WorldState) {
+    // only check while moving up
+    if self.vel_y >= 0.0 {
+      return;
+    }
+
+    // cast from inside the head upward, one ray near each edge
+    let reach = 12.0;
+    let mut sensors = vec![
+      Ray::new(
+        [
+          self.transform.scene_x + self.collider.left + 4.0,
+          self.transform.scene_y + self.collider.top + reach,
+        ],
+        [0.0, -1.0],
+        reach,
+        reach as u8,
+      ),
+      Ray::new(
+        [
+          self.transform.scene_x + self.collider.right - 4.0,
+          self.transform.scene_y + self.collider.top + reach,
+        ],
+        [0.0, -1.0],
+        reach,
+        reach as u8,
+      ),
+    ];
+
+    let tile_height = bus.ppu.tile_maps[world.collision_set.tile_set_id].tile_height as f32;
+    let mut bumped = false;
+    let mut push_down: f32 = 0.0;
+
+    for sensor in sensors.iter_mut() {
+      let (mc_idx, hit) = get_nearest_map_collision(sensor, bus, world);
+      if hit {
+        let hit_mc = &sensor.map_collided[mc_idx];
+        if self.find_surface_height(hit_mc.tile_id, hit_mc.point[0], world) == 16.0 {
+          // push the head back out to the underside of the tile
+          let ceiling = (hit_mc.point[1] / tile_height).floor() * tile_height + tile_height;
+          let overlap = ceiling - (self.transform.scene_y + self.collider.top);
+          push_down = push_down.max(overlap);
+          bumped = true;
+        }
       }
+    }
 
-      // if hit detected early (up to 8 pixels), correct
-      let err = hit_mc.point[1] - sensors[sensor_idx].origin[1];
-
-      /*println!("searched from {:?} detected neared hit at {:?} which overlaps {:?}",
-      sensors[sensor_idx].origin,
-      hit_mc.point,
-      sensors[sensor_idx].map_collided[mc_idx].overlap[1] + err
-      );*/
-      self.transform.translate(
-        0.0,
-        &sensors[sensor_idx].map_collided[mc_idx].overlap[1] + err + (16.0 - self.slope),
-      );
+    if bumped {
+      self.vel_y = 0.0;
+      self.transform.translate(0.0, push_down);
     }
   }
 
@@ -493,31 +811,44 @@ impl Player {
       offset = self.collider.left;
     }
 
-    let mut sensors = vec![Ray::new(
-      [
-        self.transform.scene_x + offset,
-        self.transform.scene_y + 16.0,
-      ],
-      [self.vel_x.signum(), 0.0],
-      4.0,
-      4,
-    )];
-
-    let (sensor_idx, mc_idx, hit) = self.get_nearest_map_collision(&mut sensors, bus, world);
-    if hit {
-      let hit_mc = &sensors[sensor_idx].map_collided[mc_idx];
-      let surface = self.find_surface_height(hit_mc.tile_id, hit_mc.point[0], world);
-      let err = hit_mc.point[0] - sensors[sensor_idx].origin[0];
-      /*println!("origin {:?} collision {:?} distance {} err {}",
-      sensors[sensor_idx].origin,
-      sensors[sensor_idx].map_collided[mc_idx],
-      distance,
-      err);*/
-      if surface == 16.0 {
-        self.blocked[side] = true;
-        self.vel_x = 0.0;
-        self.transform.translate(err, 0.0);
+    // one sensor per tile row from the head down to step height,
+    // anything lower is left to the ground sensor (slopes, steps)
+    let mut sensors = vec![];
+    let mut height = self.collider.top + 4.0;
+    while height <= self.collider.bottom - self.step_height {
+      sensors.push(Ray::new(
+        [
+          self.transform.scene_x + offset,
+          self.transform.scene_y + height,
+        ],
+        [self.vel_x.signum(), 0.0],
+        4.0,
+        4,
+      ));
+      height += 16.0;
+    }
+
+    let mut hit_wall = false;
+    let mut correction: f32 = 0.0;
+
+    for sensor in sensors.iter_mut() {
+      let (mc_idx, hit) = get_nearest_map_collision(sensor, bus, world);
+      if hit {
+        let hit_mc = &sensor.map_collided[mc_idx];
+        let surface = self.find_surface_height(hit_mc.tile_id, hit_mc.point[0], world);
+        let err = hit_mc.point[0] - sensor.origin[0];
+        // nearest wall wins
+        if surface == 16.0 && (!hit_wall || err.abs() < correction.abs()) {
+          hit_wall = true;
+          correction = err;
+        }
       }
+    }
+
+    if hit_wall {
+      self.blocked[side] = true;
+      self.vel_x = 0.0;
+      self.transform.translate(correction, 0.0);
     } else {
       self.blocked[side] = false;
     }
@@ -565,36 +896,18 @@ impl Player {
     }
   }
 
-  pub fn get_nearest_map_collision(
-    &mut self,
-    sensors: &mut Vec<Ray>,
-    bus: &LentSysBus,
-    world: &WorldState,
-  ) -> (usize, usize, bool) {
-    let mut hit = false;
-    let mut sensor_idx = 0;
-    let mut mc_idx = 0;
-    let mut nearest_collision_distance = 1.0e6;
-    for (i, s) in sensors.iter_mut().enumerate() {
-      s.check_tile_collision(
-        [0.0, 0.0],
-        &bus.ppu.tile_maps[world.collision_set.tile_set_id],
-      );
-      for (j, mc) in s.map_collided.iter().enumerate() {
-        if world.collision_set.tiles.contains_key(&mc.tile_id) {
-          let distance_from_collision = (s.origin[1] - mc.point[1]).abs();
-          if distance_from_collision < nearest_collision_distance {
-            nearest_collision_distance = distance_from_collision;
-            sensor_idx = i;
-            mc_idx = j;
-          }
-
-          hit = true;
-        }
+  // Slope in degrees, positive when it runs downhill in the moving direction
+  pub fn find_surface_degrees(&mut self, tile_id: usize, world: &WorldState) -> f32 {
+    let tile_attr = world.collision_set.tiles.get(&tile_id);
+    match tile_attr {
+      Some(TileAttr::Angle(ang)) => {
+        let degree_table = [0.0, 45.0, 27.0, 27.0, 11.0];
+        return self.vel_x.signum() * -ang.signum() as f32 * degree_table[ang.abs() as usize];
+      }
+      _ => {
+        return 0.0;
       }
     }
-
-    return (sensor_idx, mc_idx, hit);
   }
 }
 
@@ -614,7 +927,7 @@ pub fn get_nearest_map_collision(
   for (j, mc) in sensor.map_collided.iter().enumerate() {
     //println!("{:?}", mc);
     if world.collision_set.tiles.contains_key(&mc.tile_id) {
-      let distance_from_collision = (sensor.origin[1] - mc.point[1]).abs();
+      let distance_from_collision = distance(sensor.origin, mc.point);
       if distance_from_collision < nearest_collision_distance {
         nearest_collision_distance = distance_from_collision;
         mc_idx = j;
@@ -626,3 +939,7 @@ pub fn get_nearest_map_collision(
 
   return (mc_idx, hit);
 }
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+  ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+// Plain `key=value` lines, so the same data can live in a file for the
+// native build or in localStorage for the web build.
+#[derive(Debug, Default)]
+pub struct SaveData {
+  pub entries: HashMap<String, String>,
+}
+
+impl SaveData {
+  pub fn parse(data: &str) -> SaveData {
+    let mut entries = HashMap::new();
+    for line in data.lines() {
+      if let Some(idx) = line.find('=') {
+        entries.insert(
+          line[..idx].trim().to_string(),
+          line[idx + 1..].trim().to_string(),
+        );
+      }
+    }
+    SaveData { entries }
+  }
+
+  pub fn serialize(&self) -> String {
+    let mut keys: Vec<&String> = self.entries.keys().collect();
+    keys.sort();
+    keys
+      .iter()
+      .map(|key| format!("{}={}\n", key, self.entries[*key]))
+      .collect()
+  }
+
+  pub fn set<T: ToString>(&mut self, key: &str, value: T) {
+    self.entries.insert(key.to_string(), value.to_string());
+  }
+
+  pub fn get_str(&self, key: &str) -> Option<&str> {
+    self.entries.get(key).map(|value| value.as_str())
+  }
+
+  pub fn get_u32(&self, key: &str, default: u32) -> u32 {
+    match self.entries.get(key) {
+      Some(value) => value.parse::<u32>().unwrap_or(default),
+      None => default,
+    }
+  }
+
+  pub fn get_f32(&self, key: &str, default: f32) -> f32 {
+    match self.entries.get(key) {
+      Some(value) => value.parse::<f32>().unwrap_or(default),
+      None => default,
+    }
+  }
+
+  pub fn get_bool(&self, key: &str, default: bool) -> bool {
+    match self.entries.get(key) {
+      Some(value) => value == "true",
+      None => default,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_splits_at_the_first_equals() {
+    let save = SaveData::parse("name = Spyder\nkey.left==\nbroken line\n");
+    assert_eq!(save.get_str("name"), Some("Spyder"));
+    assert_eq!(save.get_str("key.left"), Some("="));
+    assert_eq!(save.entries.len(), 2);
+  }
+
+  #[test]
+  fn typed_getters_fall_back_to_the_default() {
+    let save = SaveData::parse("shots=12\nlevel=0.5\nrumble=true\nbad=x\n");
+    assert_eq!(save.get_u32("shots", 0), 12);
+    assert_eq!(save.get_u32("bad", 7), 7);
+    assert_eq!(save.get_u32("missing", 3), 3);
+    assert_eq!(save.get_f32("level", 1.0), 0.5);
+    assert_eq!(save.get_f32("bad", 1.0), 1.0);
+    assert!(save.get_bool("rumble", false));
+    assert!(!save.get_bool("bad", true));
+    assert!(save.get_bool("missing", true));
+  }
+
+  #[test]
+  fn serialize_round_trips_in_key_order() {
+    let mut save = SaveData::default();
+    save.set("b", 2);
+    save.set("a", "one");
+    save.set("c", 0.25);
+    let data = save.serialize();
+    assert_eq!(data, "a=one\nb=2\nc=0.25\n");
+    assert_eq!(SaveData::parse(&data).entries, save.entries);
+  }
+}
@@ -0,0 +1,93 @@
+use crate::game::bindings::KeyBindings;
+use crate::game::save::SaveData;
+use crate::game::state::GameMode;
+
+pub struct Settings {
+  pub master_volume: u8,
+  pub music_volume: u8,
+  pub sfx_volume: u8,
+  pub window_scale: u8,
+  pub fullscreen: bool,
+  pub default_game: GameMode,
+  pub key_bindings: KeyBindings,
+  // percent of analog stick travel ignored
+  pub dead_zone: u8,
+}
+
+pub const MAX_VOLUME: u8 = 10;
+pub const MAX_WINDOW_SCALE: u8 = 4;
+pub const MAX_DEAD_ZONE: u8 = 90;
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      master_volume: MAX_VOLUME,
+      music_volume: MAX_VOLUME,
+      sfx_volume: MAX_VOLUME,
+      window_scale: 2,
+      fullscreen: false,
+      default_game: GameMode::Buglympics,
+      key_bindings: KeyBindings::default(),
+      dead_zone: 20,
+    }
+  }
+}
+
+impl Settings {
+  pub fn master_level(&self) -> f32 {
+    self.master_volume as f32 / MAX_VOLUME as f32
+  }
+
+  pub fn music_level(&self) -> f32 {
+    self.music_volume as f32 / MAX_VOLUME as f32
+  }
+
+  pub fn sfx_level(&self) -> f32 {
+    self.sfx_volume as f32 / MAX_VOLUME as f32
+  }
+
+  pub fn read(save: &SaveData) -> Settings {
+    let defaults = Settings::default();
+    Settings {
+      master_volume: save
+        .get_u32("audio.master", defaults.master_volume as u32)
+        .min(MAX_VOLUME as u32) as u8,
+      music_volume: save
+        .get_u32("audio.music", defaults.music_volume as u32)
+        .min(MAX_VOLUME as u32) as u8,
+      sfx_volume: save
+        .get_u32("audio.sfx", defaults.sfx_volume as u32)
+        .min(MAX_VOLUME as u32) as u8,
+      window_scale: save
+        .get_u32("video.scale", defaults.window_scale as u32)
+        .max(1)
+        .min(MAX_WINDOW_SCALE as u32) as u8,
+      fullscreen: save.get_bool("video.fullscreen", defaults.fullscreen),
+      default_game: match save.get_str("game.default_mode") {
+        Some("spyder") => GameMode::Spyder,
+        _ => GameMode::Buglympics,
+      },
+      key_bindings: KeyBindings::read(save),
+      dead_zone: save
+        .get_u32("pad.dead_zone", defaults.dead_zone as u32)
+        .min(MAX_DEAD_ZONE as u32) as u8,
+    }
+  }
+
+  pub fn write(&self, save: &mut SaveData) {
+    save.set("audio.master", self.master_volume);
+    save.set("audio.music", self.music_volume);
+    save.set("audio.sfx", self.sfx_volume);
+    save.set("video.scale", self.window_scale);
+    save.set("video.fullscreen", self.fullscreen);
+    save.set(
+      "game.default_mode",
+      match self.default_game {
+        GameMode::Buglympics => "buglympics",
+        GameMode::Spyder => "spyder",
+      },
+    );
+    self.key_bindings.write(save);
+    save.set("pad.dead_zone", self.dead_zone);
+  }
+}
@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+
+use lentsys::apu::music::AudioSource;
+use lentsys::apu::synth::{AmpEnvelope, Instrument, WaveForm};
+use lentsys::lentsys::LentSysBus;
+
+use crate::game::save::SaveData;
+use crate::game::state::GameMode;
+
+// two pulses, a triangle and noise unless the song says otherwise
+pub const CHANNELS: usize = 4;
+// songs played together, one for each game
+pub const MAX_LAYERS: usize = 2;
+const DEFAULT_VOICES: [&str; CHANNELS] = ["pulse", "pulse", "triangle", "noise"];
+pub const MAX_VOLUME: i32 = 15;
+// instrument volume for a full volume note
+pub const MUSIC_VOLUME: f32 = 0.25;
+// seconds to fade between layers
+pub const CROSSFADE_TIME: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pitch {
+  // semitones above C0
+  Note(i32),
+  Off,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cell {
+  pub pitch: Option<Pitch>,
+  pub volume: Option<i32>,
+  pub instrument: Option<usize>,
+}
+
+// Steps once per tick, then holds its last value or jumps back to the loop
+#[derive(Debug, Clone, Default)]
+pub struct Macro {
+  pub values: Vec<i32>,
+  pub loop_at: Option<usize>,
+}
+
+impl Macro {
+  pub fn at(&self, tick: usize) -> Option<i32> {
+    let len = self.values.len();
+    if len == 0 {
+      return None;
+    }
+
+    let idx = match self.loop_at {
+      Some(start) if tick >= len && start < len => start + (tick - len) % (len - start),
+      _ => tick.min(len - 1),
+    };
+    Some(self.values[idx])
+  }
+
+  fn read(save: &SaveData, key: &str) -> Macro {
+    Macro {
+      values: save
+        .get_str(key)
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|value| value.parse::<i32>().ok())
+        .collect(),
+      loop_at: save
+        .get_str(&format!("{}_loop", key))
+        .and_then(|value| value.parse::<usize>().ok()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SongInstrument {
+  pub volume: Macro,
+  // semitones added to the note
+  pub arpeggio: Macro,
+  // pulse duty, 0 to 3 for 12.5% to 75%
+  pub duty: Macro,
+}
+
+// Tracker song as written by the DMF import at pack time, e.g.
+//   event_select_theme.ticks=3,3
+//   event_select_theme.voices=pulse,pulse,triangle,noise
+//   event_select_theme.order.0=0,3,0,3,3
+//   event_select_theme.pattern.0.3=0:26:15:1;8:26:15:1;23:off:-:-
+//   event_select_theme.instrument.1.volume=15,14,13
+// with pattern cells as row:pitch:volume:instrument and '-' for empty.
+#[derive(Debug, Clone)]
+pub struct Song {
+  // ticks per row, alternating between rows
+  pub ticks: [usize; 2],
+  pub hz: f32,
+  pub rows: usize,
+  // waveform of each channel, pulse, triangle or noise
+  pub voices: Vec<String>,
+  // pattern played by each channel at each step of the song
+  pub order: Vec<Vec<usize>>,
+  pub patterns: Vec<HashMap<usize, Vec<Cell>>>,
+  pub instruments: Vec<SongInstrument>,
+}
+
+impl Song {
+  pub fn read(save: &SaveData, name: &str) -> Option<Song> {
+    let key = |field: &str| format!("{}.{}", name, field);
+
+    let ticks: Vec<usize> = save
+      .get_str(&key("ticks"))?
+      .split(',')
+      .filter_map(|tick| tick.parse::<usize>().ok())
+      .collect();
+    if ticks.len() != 2 {
+      return None;
+    }
+
+    let rows = save.get_u32(&key("rows"), 64) as usize;
+    let mut voices: Vec<String> = save
+      .get_str(&key("voices"))
+      .unwrap_or("")
+      .split(',')
+      .filter(|voice| !voice.is_empty())
+      .map(|voice| voice.trim().to_string())
+      .collect();
+    for voice in DEFAULT_VOICES.iter().skip(voices.len()) {
+      voices.push(voice.to_string());
+    }
+    let mut order = vec![];
+    let mut patterns = vec![];
+    for channel in 0..CHANNELS {
+      let steps: Vec<usize> = save
+        .get_str(&key(&format!("order.{}", channel)))
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|idx| idx.parse::<usize>().ok())
+        .collect();
+
+      let mut channel_patterns = HashMap::new();
+      for idx in steps.iter() {
+        let cells = save
+          .get_str(&key(&format!("pattern.{}.{}", channel, idx)))
+          .unwrap_or("");
+        channel_patterns.insert(*idx, read_cells(cells, rows));
+      }
+
+      order.push(steps);
+      patterns.push(channel_patterns);
+    }
+
+    let mut instruments = vec![];
+    for idx in 0..save.get_u32(&key("instruments"), 0) {
+      let macro_key = |field: &str| key(&format!("instrument.{}.{}", idx, field));
+      instruments.push(SongInstrument {
+        volume: Macro::read(save, &macro_key("volume")),
+        arpeggio: Macro::read(save, &macro_key("arpeggio")),
+        duty: Macro::read(save, &macro_key("duty")),
+      });
+    }
+
+    Some(Song {
+      ticks: [ticks[0].max(1), ticks[1].max(1)],
+      hz: save.get_f32(&key("hz"), 60.0),
+      rows,
+      voices,
+      order,
+      patterns,
+      instruments,
+    })
+  }
+
+  pub fn steps(&self) -> usize {
+    self.order.iter().map(|steps| steps.len()).max().unwrap_or(0)
+  }
+
+  pub fn cell(&self, channel: usize, step: usize, row: usize) -> Option<&Cell> {
+    let idx = self.order.get(channel)?.get(step)?;
+    self.patterns[channel].get(idx)?.get(row)
+  }
+}
+
+fn read_cells(data: &str, rows: usize) -> Vec<Cell> {
+  let mut cells = vec![Cell::default(); rows];
+  for entry in data.split(';') {
+    let fields: Vec<&str> = entry.split(':').collect();
+    if fields.len() != 4 {
+      continue;
+    }
+
+    let row = match fields[0].parse::<usize>() {
+      Ok(row) if row < rows => row,
+      _ => continue,
+    };
+
+    cells[row] = Cell {
+      pitch: match fields[1] {
+        "off" => Some(Pitch::Off),
+        pitch => pitch.parse::<i32>().ok().map(Pitch::Note),
+      },
+      volume: fields[2].parse::<i32>().ok(),
+      instrument: fields[3].parse::<usize>().ok(),
+    };
+  }
+  cells
+}
+
+// The songs listed in `songs=` of the packed song data
+pub fn read_songs(data: &str) -> HashMap<String, Song> {
+  let save = SaveData::parse(data);
+  save
+    .get_str("songs")
+    .unwrap_or("")
+    .split(',')
+    .filter_map(|name| Song::read(&save, name).map(|song| (name.to_string(), song)))
+    .collect()
+}
+
+// The songs each scene plays, from `scene.<index>.` keys of the song data
+pub fn read_scenes(data: &str) -> HashMap<usize, SceneMusic> {
+  let save = SaveData::parse(data);
+  let mut scenes = HashMap::new();
+  for key in save.entries.keys() {
+    let scene = match key
+      .strip_prefix("scene.")
+      .and_then(|key| key.strip_suffix(".songs"))
+      .and_then(|scene| scene.parse::<usize>().ok())
+    {
+      Some(scene) => scene,
+      None => continue,
+    };
+
+    let field = |name: &str| format!("scene.{}.{}", scene, name);
+    let songs = save
+      .get_str(key)
+      .unwrap_or("")
+      .split(',')
+      .map(|name| name.trim().to_string())
+      .filter(|name| !name.is_empty())
+      .take(MAX_LAYERS)
+      .collect();
+    let arrangement = Arrangement {
+      transpose: save
+        .get_str(&field("transpose"))
+        .and_then(|value| value.parse::<i32>().ok())
+        .unwrap_or(0),
+      tempo: save.get_f32(&field("tempo"), 1.0),
+    };
+    scenes.insert(scene, SceneMusic { songs, arrangement });
+  }
+  scenes
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Voice {
+  pitch: Option<i32>,
+  volume: i32,
+  instrument: Option<usize>,
+  // ticks since the note started, for the instrument macros
+  tick: usize,
+}
+
+#[derive(Debug)]
+struct Cursor {
+  started: bool,
+  step: usize,
+  row: usize,
+  tick: usize,
+  voices: [Voice; CHANNELS],
+}
+
+impl Default for Cursor {
+  fn default() -> Self {
+    Self {
+      started: false,
+      step: 0,
+      row: 0,
+      tick: 0,
+      voices: [Voice {
+        volume: MAX_VOLUME,
+        ..Voice::default()
+      }; CHANNELS],
+    }
+  }
+}
+
+impl Cursor {
+  fn advance(&mut self, song: &Song) {
+    for voice in self.voices.iter_mut() {
+      voice.tick += 1;
+    }
+
+    if self.tick == 0 {
+      for (channel, voice) in self.voices.iter_mut().enumerate() {
+        let cell = match song.cell(channel, self.step, self.row) {
+          Some(cell) => cell,
+          None => continue,
+        };
+
+        if let Some(instrument) = cell.instrument {
+          voice.instrument = Some(instrument);
+        }
+
+        if let Some(volume) = cell.volume {
+          voice.volume = volume;
+        }
+
+        match cell.pitch {
+          Some(Pitch::Note(pitch)) => {
+            voice.pitch = Some(pitch);
+            voice.tick = 0;
+          }
+          Some(Pitch::Off) => voice.pitch = None,
+          None => {}
+        }
+      }
+    }
+
+    self.tick += 1;
+    if self.tick >= song.ticks[self.row % 2] {
+      self.tick = 0;
+      self.row += 1;
+      if self.row >= song.rows {
+        self.row = 0;
+        self.step = (self.step + 1) % song.steps().max(1);
+      }
+    }
+  }
+}
+
+// How a scene plays its songs, so one tune can serve several scenes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arrangement {
+  // semitones
+  pub transpose: i32,
+  // multiplies the songs' tick rate
+  pub tempo: f32,
+}
+
+impl Default for Arrangement {
+  fn default() -> Self {
+    Self {
+      transpose: 0,
+      tempo: 1.0,
+    }
+  }
+}
+
+// The songs a scene plays, in layer order, and how
+#[derive(Debug, Clone, Default)]
+pub struct SceneMusic {
+  pub songs: Vec<String>,
+  pub arrangement: Arrangement,
+}
+
+// One of the songs played together, on its own synths
+struct Layer {
+  name: String,
+  cursor: Cursor,
+  // 0.0 to 1.0, faded towards 1.0 for the focused layer
+  level: f32,
+  synth_base: usize,
+}
+
+// Plays songs in layers on a shared clock, carrying on through scenes that
+// share them. Layers are meant to have the same tempo and length, so one can
+// be faded into the other and stay on the beat.
+pub struct SongPlayer {
+  pub songs: HashMap<String, Song>,
+  pub scenes: HashMap<usize, SceneMusic>,
+  layers: Vec<Layer>,
+  focus: usize,
+  arrangement: Arrangement,
+  // seconds into the current tick
+  time: f32,
+}
+
+impl SongPlayer {
+  pub fn new() -> SongPlayer {
+    SongPlayer {
+      songs: HashMap::new(),
+      scenes: HashMap::new(),
+      layers: vec![],
+      focus: 0,
+      arrangement: Arrangement::default(),
+      time: 0.0,
+    }
+  }
+
+  // packed song data, with the songs and the scenes that play them
+  pub fn load(&mut self, data: &str) {
+    self.songs = read_songs(data);
+    self.scenes = read_scenes(data);
+  }
+
+  // Scene loads replace the synths, so this is called after every load.
+  // The songs only restart when they or their arrangement change.
+  pub fn play_scene(&mut self, bus: &mut LentSysBus, scene: usize, focus: usize) {
+    let music = self.scenes.get(&scene).cloned().unwrap_or_default();
+    let names: Vec<&str> = music.songs.iter().map(|name| name.as_str()).collect();
+    self.play(bus, &names, focus, music.arrangement);
+  }
+
+  pub fn play(
+    &mut self,
+    bus: &mut LentSysBus,
+    names: &[&str],
+    focus: usize,
+    arrangement: Arrangement,
+  ) {
+    let names = &names[..names.len().min(MAX_LAYERS)];
+    let playing: Vec<&str> = self.layers.iter().map(|layer| layer.name.as_str()).collect();
+    if playing != names || self.arrangement != arrangement {
+      self.arrangement = arrangement;
+      self.layers = names
+        .iter()
+        .map(|name| Layer {
+          name: name.to_string(),
+          cursor: Cursor::default(),
+          level: 0.0,
+          synth_base: 0,
+        })
+        .collect();
+      self.time = 0.0;
+    }
+
+    // a new scene starts on its layer rather than fading to it
+    self.focus(focus);
+    for (idx, layer) in self.layers.iter_mut().enumerate() {
+      layer.level = if idx == self.focus { 1.0 } else { 0.0 };
+      layer.synth_base = prepare_instruments(bus, self.songs.get(&layer.name));
+    }
+  }
+
+  // fade to another layer
+  pub fn focus(&mut self, layer: usize) {
+    self.focus = layer.min(self.layers.len().saturating_sub(1));
+  }
+
+  // Step the songs on by `time_delta` and queue what each channel is
+  // playing for the next `samples`, scaled by the music setting
+  pub fn render(
+    &mut self,
+    bus: &mut LentSysBus,
+    time_delta: f32,
+    music_level: f32,
+    samples: usize,
+    queue: &mut Vec<(f32, AudioSource, usize, usize)>,
+  ) {
+    let songs = &self.songs;
+
+    // the first song sets the clock for them all
+    let hz = match self.layers.iter().find_map(|layer| songs.get(&layer.name)) {
+      Some(song) => song.hz,
+      None => return,
+    };
+
+    let tick_time = 1.0 / (hz * self.arrangement.tempo);
+    let mut ticks = 0;
+    self.time += time_delta;
+    while self.time >= tick_time {
+      self.time -= tick_time;
+      ticks += 1;
+    }
+
+    let fade = time_delta / CROSSFADE_TIME;
+    for (idx, layer) in self.layers.iter_mut().enumerate() {
+      let song = match songs.get(&layer.name) {
+        Some(song) => song,
+        None => continue,
+      };
+
+      // the first row plays straight away
+      if !layer.cursor.started {
+        layer.cursor.started = true;
+        layer.cursor.advance(song);
+      }
+      for _ in 0..ticks {
+        layer.cursor.advance(song);
+      }
+
+      layer.level = if idx == self.focus {
+        (layer.level + fade).min(1.0)
+      } else {
+        (layer.level - fade).max(0.0)
+      };
+
+      if layer.level > 0.0 && bus.apu.synths.len() >= layer.synth_base + CHANNELS {
+        let transpose = self.arrangement.transpose;
+        queue_voices(bus, song, layer, music_level, transpose, samples, queue);
+      }
+    }
+  }
+}
+
+fn prepare_instruments(bus: &mut LentSysBus, song: Option<&Song>) -> usize {
+  let synth_base = bus.apu.synths.len();
+
+  for channel in 0..CHANNELS {
+    let voice = match song {
+      Some(song) => song.voices[channel].as_str(),
+      None => DEFAULT_VOICES[channel],
+    };
+
+    bus.apu.synths.push(Instrument {
+      volume: MUSIC_VOLUME,
+      waveform: match voice {
+        "noise" => WaveForm::NOISE,
+        "triangle" => WaveForm::TRIANGLE,
+        _ => WaveForm::PULSE,
+      },
+      duty: 0.5,
+      amp_envelope: AmpEnvelope {
+        attack: 0.0,
+        decay: 0.0,
+        sustain: 0.0,
+        sustain_level: 1.0,
+        release: 1.0,
+        ..AmpEnvelope::default()
+      },
+      ..Instrument::default()
+    });
+  }
+
+  synth_base
+}
+
+fn queue_voices(
+  bus: &mut LentSysBus,
+  song: &Song,
+  layer: &Layer,
+  music_level: f32,
+  transpose: i32,
+  samples: usize,
+  queue: &mut Vec<(f32, AudioSource, usize, usize)>,
+) {
+  for (channel, voice) in layer.cursor.voices.iter().enumerate() {
+    let pitch = match voice.pitch {
+      Some(pitch) => pitch,
+      None => continue,
+    };
+
+    let instrument = voice.instrument.and_then(|idx| song.instruments.get(idx));
+    let envelope = instrument
+      .and_then(|instrument| instrument.volume.at(voice.tick))
+      .unwrap_or(MAX_VOLUME);
+    let arpeggio = instrument
+      .and_then(|instrument| instrument.arpeggio.at(voice.tick))
+      .unwrap_or(0);
+    let duty = instrument
+      .and_then(|instrument| instrument.duty.at(voice.tick))
+      .unwrap_or(2);
+
+    let level = (voice.volume * envelope) as f32 / (MAX_VOLUME * MAX_VOLUME) as f32;
+    if level <= 0.0 {
+      continue;
+    }
+
+    let synth = &mut bus.apu.synths[layer.synth_base + channel];
+    synth.volume = MUSIC_VOLUME * level * layer.level * music_level;
+    if song.voices[channel] == "pulse" {
+      synth.duty = [0.125, 0.25, 0.5, 0.75][duty.clamp(0, 3) as usize];
+    }
+
+    // noise is unpitched, so it isn't transposed
+    let transpose = if song.voices[channel] == "noise" { 0 } else { transpose };
+    queue.push((
+      note_frequency(pitch + arpeggio + transpose),
+      AudioSource::Instrument,
+      layer.synth_base + channel,
+      samples,
+    ));
+  }
+}
+
+// layer for each game's take on a scene's music
+pub fn mode_layer(game: GameMode) -> usize {
+  match game {
+    GameMode::Buglympics => 0,
+    GameMode::Spyder => 1,
+  }
+}
+
+// equal temperament from A4 at 440Hz
+pub fn note_frequency(pitch: i32) -> f32 {
+  440.0 * 2f32.powf((pitch - 57) as f32 / 12.0)
+}
@@ -3,6 +3,17 @@ use lentsys::apu::synth::Instrument;
 use lentsys::apu::synth::{AmpEnvelope, WaveForm};
 use lentsys::apu::music::AudioSource;
 
+use crate::game::save::SaveData;
+
+// instrument volume at full SFX level
+pub const EFFECT_VOLUME: f32 = 0.4;
+// synths pushed by prepare_effects, any after them belong to the music's
+// layers, so the APU is made with room for both
+pub const EFFECT_SYNTHS: usize = 8;
+// built in so the game has effects before any packed data is loaded
+const DEFAULT_EFFECTS: &str = include_str!("../../assets/effects.sfx");
+
+#[derive(Debug, Clone, Copy)]
 pub enum SFX {
   JumpA,
   JumpB,
@@ -10,78 +21,241 @@ pub enum SFX {
   Ski,
   Select,
   Switch,
+  Denied,
 }
 
-pub fn prepare_effects(bus: &mut LentSysBus){
-  bus.apu.synths.push(Instrument {
-    volume: 0.4,
-    waveform: WaveForm::NOISE,
-    duty: 0.75,
-    amp_envelope: AmpEnvelope{
-      attack: 0.0,
-      decay: 0.0,
-      sustain: 0.0,
-      sustain_level: 1.0,
-      release: 1.0,
-      ..AmpEnvelope::default()
-    },
-    ..Instrument::default()
-  });
-
-  bus.apu.synths.push(Instrument {
-    volume: 0.4,
-    waveform: WaveForm::PULSE,
-    duty: 0.6,
-    amp_envelope: AmpEnvelope{
-      attack: 0.0,
-      decay: 0.0,
-      sustain: 0.0,
-      sustain_level: 1.0,
-      release: 1.0,
-      ..AmpEnvelope::default()
-    },
-    ..Instrument::default()
-  });
-
-  bus.apu.synths.push(Instrument {
-    volume: 0.4,
-    waveform: WaveForm::TRIANGLE,
-    duty: 0.5,
-    amp_envelope: AmpEnvelope{
-      attack: 0.0,
-      decay: 0.0,
-      sustain: 0.0,
-      sustain_level: 1.0,
-      release: 1.0,
-      ..AmpEnvelope::default()
-    },
-    ..Instrument::default()
-  });
+impl SFX {
+  // in index order, as effects are requested by index
+  pub const ALL: [SFX; 7] = [
+    SFX::JumpA,
+    SFX::JumpB,
+    SFX::Fire,
+    SFX::Ski,
+    SFX::Select,
+    SFX::Switch,
+    SFX::Denied,
+  ];
+
+  // the effect's name in the effects data
+  pub fn name(&self) -> &'static str {
+    match self {
+      SFX::JumpA => "jump_a",
+      SFX::JumpB => "jump_b",
+      SFX::Fire => "fire",
+      SFX::Ski => "ski",
+      SFX::Select => "select",
+      SFX::Switch => "switch",
+      SFX::Denied => "denied",
+    }
+  }
+}
 
+#[derive(Debug, Clone)]
+pub struct EffectDef {
+  pub voice: usize,
+  pub waveform: String,
+  pub duty: f32,
+  pub attack: f32,
+  pub decay: f32,
+  pub sustain: f32,
+  pub sustain_level: f32,
+  pub release: f32,
+  pub frequency: f32,
+  // Hz per second
+  pub sweep: f32,
+  pub duration: usize,
 }
 
-pub fn play_effect(bus: &mut LentSysBus, effect: SFX, num_samples: usize){
-  bus.apu.synths[1].counter = 0;
-  bus.apu.synths[2].counter = 0;
-  //bus.apu.synths[0].counter = 0;
-  match effect {
-    SFX::JumpA => {
-      bus.apu.fx_queue.push((200.0, AudioSource::Instrument, 2, num_samples));
-    },
-    SFX::JumpB => {
-      bus.apu.fx_queue.push((110.0, AudioSource::Instrument, 1, num_samples));
-    },
-    SFX::Ski => {
-      bus.apu.fx_queue.push((20.0, AudioSource::Instrument, 0, num_samples));
-    },
-    SFX::Fire => {
-      bus.apu.fx_queue.push((10.0, AudioSource::Instrument, 1, num_samples));
-    },
-    SFX::Select => {
-      bus.apu.fx_queue.push((300.0, AudioSource::Instrument, 2, num_samples));
-    },
-    SFX::Switch => {
-      bus.apu.fx_queue.push((500.0, AudioSource::Instrument, 2, num_samples));
+impl EffectDef {
+  fn read(save: &SaveData, name: &str, voices: usize) -> EffectDef {
+    let key = |field: &str| format!("effect.{}.{}", name, field);
+    EffectDef {
+      voice: (save.get_u32(&key("voice"), 0) as usize).min(voices - 1),
+      waveform: save.get_str(&key("waveform")).unwrap_or("pulse").to_string(),
+      duty: save.get_f32(&key("duty"), 0.5),
+      attack: save.get_f32(&key("attack"), 0.0),
+      decay: save.get_f32(&key("decay"), 0.0),
+      sustain: save.get_f32(&key("sustain"), 0.0),
+      sustain_level: save.get_f32(&key("sustain_level"), 1.0),
+      release: save.get_f32(&key("release"), 1.0),
+      frequency: save.get_f32(&key("frequency"), 440.0),
+      sweep: save.get_f32(&key("sweep"), 0.0),
+      duration: save.get_u32(&key("duration"), 800) as usize,
     }
   }
-}
\ No newline at end of file
+
+  fn waveform(&self) -> WaveForm {
+    match self.waveform.as_str() {
+      "noise" => WaveForm::NOISE,
+      "triangle" => WaveForm::TRIANGLE,
+      _ => WaveForm::PULSE,
+    }
+  }
+}
+
+// An effect part way through, on its voice
+#[derive(Debug, Clone, Copy)]
+struct Playing {
+  effect: usize,
+  elapsed: usize,
+}
+
+// Effects are requested through the bus' fx_queue by play_effect, then
+// started and swept here as audio is rendered
+pub struct SoundEffects {
+  // in SFX order
+  pub effects: Vec<EffectDef>,
+  // how many of the effect synths are played on
+  pub voices: usize,
+  playing: [Option<Playing>; EFFECT_SYNTHS],
+}
+
+impl Default for SoundEffects {
+  fn default() -> Self {
+    let mut sound_effects = Self {
+      effects: vec![],
+      voices: 1,
+      playing: [None; EFFECT_SYNTHS],
+    };
+    sound_effects.load(DEFAULT_EFFECTS);
+    sound_effects
+  }
+}
+
+impl SoundEffects {
+  // Read the effects listed in the data's `effects` line. Effects it leaves
+  // out keep their current definition. Returns what didn't match the game's
+  // effects, data without an effects line is ignored.
+  pub fn load(&mut self, data: &str) -> Vec<String> {
+    let save = SaveData::parse(data);
+    let mut problems = vec![];
+    let listed: Vec<&str> = match save.get_str("effects") {
+      Some(names) => names.split(',').map(|name| name.trim()).collect(),
+      None => return problems,
+    };
+
+    let voices = save.get_u32("voices", self.voices as u32) as usize;
+    if voices == 0 || voices > EFFECT_SYNTHS {
+      problems.push(format!("voices={} is outside 1 to {}", voices, EFFECT_SYNTHS));
+    }
+    self.voices = voices.clamp(1, EFFECT_SYNTHS);
+
+    for name in listed.iter() {
+      if !SFX::ALL.iter().any(|effect| effect.name() == *name) {
+        problems.push(format!("effect {} isn't one the game plays", name));
+      }
+    }
+
+    let voices = self.voices;
+    let current = std::mem::take(&mut self.effects);
+    self.effects = SFX::ALL
+      .iter()
+      .enumerate()
+      .map(|(idx, effect)| {
+        let name = effect.name();
+        if listed.contains(&name) {
+          return EffectDef::read(&save, name, voices);
+        }
+
+        problems.push(format!("effect {} has no definition", name));
+        match current.get(idx) {
+          Some(def) => EffectDef {
+            voice: def.voice.min(voices - 1),
+            ..def.clone()
+          },
+          None => EffectDef::read(&save, name, voices),
+        }
+      })
+      .collect();
+
+    // a voice may have gone with the new count
+    for playing in self.playing.iter_mut().skip(voices) {
+      *playing = None;
+    }
+
+    problems
+  }
+
+  pub fn render(
+    &mut self,
+    bus: &mut LentSysBus,
+    samples: usize,
+    queue: &mut Vec<(f32, AudioSource, usize, usize)>,
+  ) {
+    if bus.apu.synths.len() < self.voices {
+      bus.apu.fx_queue.clear();
+      return;
+    }
+
+    for (_, _, idx, _) in bus.apu.fx_queue.drain(..) {
+      let effect = match self.effects.get(idx) {
+        Some(effect) => effect,
+        None => continue,
+      };
+
+      let synth = &mut bus.apu.synths[effect.voice];
+      synth.waveform = effect.waveform();
+      synth.duty = effect.duty;
+      synth.amp_envelope = AmpEnvelope {
+        attack: effect.attack,
+        decay: effect.decay,
+        sustain: effect.sustain,
+        sustain_level: effect.sustain_level,
+        release: effect.release,
+        ..AmpEnvelope::default()
+      };
+      synth.counter = 0;
+      self.playing[effect.voice] = Some(Playing { effect: idx, elapsed: 0 });
+    }
+
+    let sample_rate = bus.apu.config.sample_rate;
+    for (voice, playing) in self.playing.iter_mut().enumerate() {
+      let current = match playing {
+        Some(current) => current,
+        None => continue,
+      };
+
+      let effect = &self.effects[current.effect];
+      let frequency = effect.frequency + effect.sweep * current.elapsed as f32 / sample_rate;
+      let length = samples.min(effect.duration.saturating_sub(current.elapsed));
+      if length > 0 && frequency > 0.0 {
+        queue.push((frequency, AudioSource::Instrument, voice, length));
+      }
+
+      current.elapsed += samples;
+      if current.elapsed >= effect.duration {
+        *playing = None;
+      }
+    }
+  }
+}
+
+// Voices for the effects, their sound is set as each effect starts
+pub fn prepare_effects(bus: &mut LentSysBus){
+  for _ in 0..EFFECT_SYNTHS {
+    bus.apu.synths.push(Instrument {
+      volume: EFFECT_VOLUME,
+      waveform: WaveForm::PULSE,
+      duty: 0.5,
+      amp_envelope: AmpEnvelope{
+        attack: 0.0,
+        decay: 0.0,
+        sustain: 0.0,
+        sustain_level: 1.0,
+        release: 1.0,
+        ..AmpEnvelope::default()
+      },
+      ..Instrument::default()
+    });
+  }
+}
+
+pub fn set_effect_volume(bus: &mut LentSysBus, volume: f32){
+  for synth in bus.apu.synths.iter_mut().take(EFFECT_SYNTHS) {
+    synth.volume = volume;
+  }
+}
+
+pub fn play_effect(bus: &mut LentSysBus, effect: SFX){
+  bus.apu.fx_queue.push((0.0, AudioSource::Instrument, effect as usize, 0));
+}
@@ -3,10 +3,17 @@ use lentsys::ppu::attr::AttrSet;
 use lentsys::apu::music::AudioSource;
 use lentsys::apu::music::MusicTracker;
 use lentsys::lentsys::LentSysBus;
+use lentsys::game_pak::scene::SceneState;
 use crate::game::player::Player;
 use crate::game::menu::Menu;
 use crate::game::cutscene::Shot;
-use crate::game::input::InputCode;
+use crate::game::input::{InputCode, InputState};
+use crate::game::achievements::{Achievements, GameEvent};
+use crate::game::save::SaveData;
+use crate::game::settings::Settings;
+use crate::game::songs::{self, SongPlayer};
+use crate::game::sounds::SoundEffects;
+use crate::game::stats::Stats;
 
 
 pub struct SpyderEvent {
@@ -15,15 +22,125 @@ pub struct SpyderEvent {
   pub time_limit: f32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpyderEventRecord {
   pub event: String,
+  pub success: bool,
   pub time_remaining: f32,
+  pub time_limit: f32,
+  pub targets_hit: usize,
+  pub targets_total: usize,
+  pub shots_fired: u32,
+  pub shots_hit: u32,
+}
+
+impl SpyderEventRecord {
+  pub fn accuracy(&self) -> f32 {
+    if self.shots_fired == 0 {
+      return 0.0;
+    }
+    self.shots_hit as f32 / self.shots_fired as f32
+  }
+
+  // share of the clock left, events without a limit count as all of it
+  pub fn time_left(&self) -> f32 {
+    if self.time_limit <= 0.0 {
+      return 1.0;
+    }
+    (self.time_remaining / self.time_limit).max(0.0).min(1.0)
+  }
+
+  // 0.0 - 1.0, weighted towards finding every device
+  pub fn rating(&self) -> f32 {
+    let targets = if self.targets_total == 0 {
+      1.0
+    } else {
+      self.targets_hit as f32 / self.targets_total as f32
+    };
+    targets * 0.5 + self.accuracy() * 0.25 + self.time_left() * 0.25
+  }
+
+  pub fn grade(&self) -> &str {
+    if !self.success {
+      return "F";
+    }
+
+    let rating = self.rating();
+    if rating >= 0.9 {
+      "A"
+    } else if rating >= 0.75 {
+      "B"
+    } else if rating >= 0.6 {
+      "C"
+    } else {
+      "D"
+    }
+  }
+
+  // 1 star for finding every device, 1 for finishing with half the clock
+  // left and 1 for 75% accuracy
+  pub fn stars(&self) -> u8 {
+    if !self.success || self.targets_hit < self.targets_total {
+      return 0;
+    }
+
+    let mut stars = 1;
+    if self.time_left() >= 0.5 {
+      stars += 1;
+    }
+    if self.accuracy() >= 0.75 {
+      stars += 1;
+    }
+    stars
+  }
+
+  pub fn beats(&self, other: &SpyderEventRecord) -> bool {
+    (self.success && !other.success)
+      || (self.success == other.success && self.rating() > other.rating())
+  }
 }
 
 pub struct SpyderState {
   pub events: HashMap<String, SpyderEvent>,
   pub results: HashMap<String, SpyderEventRecord>,
+  pub best: HashMap<String, SpyderEventRecord>,
+  pub stars: HashMap<String, u8>,
+  pub stars_required: u8,
+  pub debrief: String,
+  // the last result went in as the event's best
+  pub new_best: bool,
+}
+
+impl SpyderState {
+  // keep the most stars earned for an event
+  pub fn award_stars(&mut self, record: &SpyderEventRecord) {
+    let earned = record.stars();
+    let stars = self.stars.entry(record.event.to_string()).or_insert(0);
+    if earned > *stars {
+      *stars = earned;
+    }
+  }
+
+  // File a finished mission, keeping it as the best if it beats the last
+  pub fn record_result(&mut self, record: SpyderEventRecord) {
+    self.new_best = match self.best.get(&record.event) {
+      Some(best) => record.beats(best),
+      None => true,
+    };
+
+    if self.new_best {
+      self.best.insert(record.event.to_string(), record.clone());
+    }
+    self.results.insert(record.event.to_string(), record);
+  }
+
+  pub fn total_stars(&self) -> u8 {
+    self.stars.values().sum()
+  }
+
+  pub fn max_stars(&self) -> u8 {
+    self.events.len() as u8 * 3
+  }
 }
 
 pub struct BuglympicsEvent {
@@ -39,9 +156,31 @@ pub struct BuglympicsState {
 
 #[derive(Debug)]
 pub struct BuglympicsEventRecord {
+  pub name: String,
   pub nation: String,
   pub event: String,
   pub time: f32,
+  pub score: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct TimeTrialRecord {
+  pub last: Option<f32>,
+  pub best: Option<f32>,
+}
+
+impl TimeTrialRecord {
+  // returns true for a new best time
+  pub fn add_time(&mut self, time: f32) -> bool {
+    self.last = Some(time);
+    match self.best {
+      Some(best) if best <= time => false,
+      _ => {
+        self.best = Some(time);
+        true
+      }
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -51,12 +190,21 @@ pub struct MedalStanding {
 }
 
 impl MedalStanding {
-  pub fn check_result(&mut self, record: BuglympicsEventRecord) {
+  // returns the medal place won, if any
+  pub fn check_result(&mut self, record: BuglympicsEventRecord) -> Option<usize> {
+    let nation = record.nation.to_string();
+    let time = record.time;
+
     self.medals.push(record);
     self
       .medals
       .sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
     self.medals.drain(3..);
+
+    self
+      .medals
+      .iter()
+      .position(|medal| medal.nation == nation && medal.time == time)
   }
 }
 
@@ -125,6 +273,8 @@ impl Target {
 pub struct SceneMap {
   pub scene: usize,
   pub both_complete: bool,
+  pub bl_complete: bool,
+  pub spy_complete: bool,
   pub bl_tm_ts: HashMap<usize, usize>,   // tile_map, tile_set
   pub bl_tm_pal: HashMap<usize, usize>,  // tile_map, palette
   pub bl_sp_ts: HashMap<usize, usize>,   // sprite, tile_set
@@ -140,6 +290,8 @@ impl Default for SceneMap {
     SceneMap {
       scene: 5,
       both_complete: false,
+      bl_complete: false,
+      spy_complete: false,
       bl_tm_ts: vec![(0, 0)].into_iter().collect(),
       bl_tm_pal: vec![(0, 0), (1, 1)].into_iter().collect(),
       bl_sp_ts: vec![(0, 2),].into_iter().collect(),
@@ -152,27 +304,78 @@ impl Default for SceneMap {
   }
 }
 
+pub enum UnlockRule {
+  EitherMode,
+  BothModes,
+}
+
+// Events in the order they are played, each unlocked by the one before it
+pub struct Campaign {
+  pub events: Vec<String>,
+  pub unlock_rule: UnlockRule,
+}
+
+impl Campaign {
+  pub fn is_complete(&self, event: &str, events: &HashMap<String, SceneMap>) -> bool {
+    match events.get(event) {
+      Some(mapping) => match self.unlock_rule {
+        UnlockRule::EitherMode => mapping.bl_complete || mapping.spy_complete,
+        UnlockRule::BothModes => mapping.both_complete,
+      },
+      None => false,
+    }
+  }
+
+  pub fn is_unlocked(&self, event: &str, events: &HashMap<String, SceneMap>) -> bool {
+    match self.events.iter().position(|e| e == event) {
+      Some(0) => true,
+      Some(idx) => self.is_complete(&self.events[idx - 1], events),
+      None => true,
+    }
+  }
+}
+
 pub struct WorldState {
   pub gravity: f32,
+  pub fall_limit: f32,
+  pub checkpoints: Vec<[f32; 2]>,
   pub collision_set: AttrSet,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameMode {
   Spyder,
   Buglympics,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum PlayMode {
+  Campaign,
+  TimeTrial,
+  Practice,
+}
+
 pub struct GameState {
   pub game: GameMode,
   pub scene_frames: u32,
-  pub swap_cooldown: u32,
-  pub input_cooldown: u32,
   pub current_scene: usize,
+  // event and medal place still waiting for a name
+  pub pending_record: Option<(String, usize)>,
+  pub player_name: String,
+  pub scene_history: Vec<usize>,
   pub current_shot: usize,
   pub event: String,
   pub last_event_success: bool,
+  pub play_mode: PlayMode,
+  pub time_trials: HashMap<String, TimeTrialRecord>,
+  pub trial_text_map: usize,
+  pub practice_point: usize,
   pub events: HashMap<String, SceneMap>,
+  pub campaign: Campaign,
+  pub event_markers: Vec<usize>,
+  pub settings: Settings,
+  // set as the options close, so the host can write the config straight away
+  pub settings_changed: bool,
   pub world: WorldState,
   pub buglympics: BuglympicsState,
   pub bl_timer: f32,
@@ -182,13 +385,29 @@ pub struct GameState {
   pub spy_finished: bool,
   pub hit_count: u8,
   pub hit_text: String,
+  pub trick_text_map: usize,
+  pub controls_text_map: usize,
+  pub name_text_map: usize,
+  pub rebinding: Option<usize>,
+  pub captured_key: Option<String>,
   pub player: Player,
+  pub stats: Stats,
+  pub lifetime_stats: Stats,
+  // session stats as the current event started, the event's own counts
+  // are what's been added since
+  pub event_stats: Stats,
+  pub achievements: Achievements,
   pub menu: Menu,
   pub music_tracker: MusicTracker,
+  // runs the effects and song mix, which has no tracker music of its own
+  pub sfx_tracker: MusicTracker,
+  pub song_player: SongPlayer,
+  pub sound_effects: SoundEffects,
   pub spyder_shots: Vec<Shot>,
   pub bl_shots: Vec<Shot>,
   pub sfx_queue: Vec<(f32, AudioSource, usize, usize)>,
-  pub inputs: HashSet<InputCode>
+  pub inputs: HashSet<InputCode>,
+  pub input_state: InputState
 }
 
 impl GameState {
@@ -244,6 +463,39 @@ impl GameState {
     self.game = GameMode::Spyder;
   }
 
+  // Move on to a scene that Cancel can come back from
+  pub fn go_to(&mut self, bus: &mut lentsys::lentsys::LentSysBus, scene: usize) {
+    bus.game_pak.scenes[self.current_scene].state = SceneState::COMPLETE;
+    self.scene_history.push(self.current_scene);
+    self.current_scene = scene;
+  }
+
+  // Return to the scene before, if there is one
+  pub fn go_back(&mut self, bus: &mut lentsys::lentsys::LentSysBus) -> bool {
+    match self.scene_history.pop() {
+      Some(scene) => {
+        bus.game_pak.scenes[self.current_scene].state = SceneState::COMPLETE;
+        self.current_scene = scene;
+        true
+      }
+      None => false,
+    }
+  }
+
+  // Show the current cutscene shot for the game being played. Shots flush
+  // the ppu, so the toast gets a new tile map after them.
+  pub fn load_shot(&mut self, bus: &mut lentsys::lentsys::LentSysBus) {
+    match &self.game {
+      GameMode::Buglympics => {
+        self.bl_shots[self.current_shot].load(bus);
+      }
+      GameMode::Spyder => {
+        self.spyder_shots[self.current_shot].load(bus);
+      }
+    }
+    self.achievements.reserve_toast(bus);
+  }
+
   pub fn check_game(&mut self, bus: &mut lentsys::lentsys::LentSysBus) {
     match &self.game {
       GameMode::Buglympics => {
@@ -256,15 +508,62 @@ impl GameState {
   }
 
   pub fn swap_game(&mut self, bus: &mut lentsys::lentsys::LentSysBus) {
-    if self.swap_cooldown > 10 {
-      self.swap_cooldown = 0;
-      match &self.game {
-        GameMode::Buglympics => {
-          self.set_spyder(bus);
-        }
-        GameMode::Spyder => {
-          self.set_buglympics(bus);
-        }
+    self.stats.swaps += 1;
+    self.achievements.notify(&GameEvent::Swapped {
+      total_swaps: self.lifetime_stats.swaps + self.stats.swaps,
+    });
+    match &self.game {
+      GameMode::Buglympics => {
+        self.set_spyder(bus);
+      }
+      GameMode::Spyder => {
+        self.set_buglympics(bus);
+      }
+    }
+
+    // fade the music over to the other game's take
+    self.song_player.focus(songs::mode_layer(self.game));
+  }
+
+  // lifetime totals include this session
+  pub fn save_data(&self) -> SaveData {
+    let mut save = SaveData::default();
+    self.lifetime_stats.combined(&self.stats).write(&mut save, "lifetime");
+    self.achievements.write(&mut save);
+    save.set("player.name", &self.player_name);
+    for (event, record) in self.time_trials.iter() {
+      if let Some(best) = record.best {
+        save.set(&format!("time_trial.{}.best", event), best);
+      }
+    }
+    save
+  }
+
+  pub fn config_data(&self) -> SaveData {
+    let mut config = SaveData::default();
+    self.settings.write(&mut config);
+    config
+  }
+
+  pub fn load_config_data(&mut self, config: &SaveData) {
+    self.settings = Settings::read(config);
+    self.game = self.settings.default_game;
+  }
+
+  pub fn load_save_data(&mut self, save: &SaveData) {
+    self.lifetime_stats = Stats::read(save, "lifetime");
+    self.achievements.read(save);
+    self.player_name = save.get_str("player.name").unwrap_or("").to_string();
+    for event in self.buglympics.events.keys() {
+      let key = format!("time_trial.{}.best", event);
+      if save.get_str(&key).is_some() {
+        self.time_trials.insert(
+          event.to_string(),
+          TimeTrialRecord {
+            last: None,
+            best: Some(save.get_f32(&key, 0.0)),
+          },
+        );
       }
     }
   }
@@ -276,6 +575,8 @@ pub fn init_game_state() -> GameState {
 
   let world = WorldState {
     gravity: 0.5,
+    fall_limit: 205.0 * 16.0,
+    checkpoints: vec![],
     collision_set: lentsys::ppu::attr::AttrSet {
       tile_set_id: 0,
       tiles: std::collections::HashMap::new(),
@@ -316,18 +617,24 @@ pub fn init_game_state() -> GameState {
           event: String::from("CROSS-COUNTRY BIATHLON"),
           medals: vec![
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 6.0,
             },
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 10.0,
             },
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 16.0,
             },
           ],
@@ -339,18 +646,24 @@ pub fn init_game_state() -> GameState {
           event: String::from("DOWNHILL BIATHLON"),
           medals: vec![
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 10.0,
             },
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 14.0,
             },
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 24.0,
             },
           ],
@@ -362,18 +675,24 @@ pub fn init_game_state() -> GameState {
           event: String::from("CRAGGY BIATHLON"),
           medals: vec![
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 12.0,
             },
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 16.0,
             },
             BuglympicsEventRecord {
+              name: String::from("BEE"),
               nation: String::from("Beehama"),
               event: String::from("biathlon"),
+              score: 0,
               time: 25.0,
             },
           ],
@@ -414,21 +733,36 @@ pub fn init_game_state() -> GameState {
     .into_iter()
     .collect(),
     results: vec![].into_iter().collect(),
+    best: vec![].into_iter().collect(),
+    stars: vec![].into_iter().collect(),
+    stars_required: 6,
+    debrief: String::from(""),
+    new_best: false,
   };
 
   let state = GameState {
     game: GameMode::Buglympics,
     scene_frames: 0,
-    input_cooldown: 0,
-    swap_cooldown: 0,
     current_scene: 0,
+    pending_record: None,
+    player_name: String::from(""),
+    scene_history: vec![],
     current_shot: 0,
     event: String::from("title_screen"),
     last_event_success: false,
+    play_mode: PlayMode::Campaign,
+    time_trials: HashMap::new(),
+    trial_text_map: 0,
+    practice_point: 0,
     bl_timer : 0.0,
     spy_timer : 120.0,
     hit_count: 0,
     hit_text: String::from(""),
+    trick_text_map: 0,
+    controls_text_map: 0,
+    name_text_map: 0,
+    rebinding: None,
+    captured_key: None,
     bl_finished : false,
     spy_finished: false,
     events: vec![
@@ -452,6 +786,8 @@ pub fn init_game_state() -> GameState {
         SceneMap {
           scene: 2,
           both_complete: false,
+          bl_complete: false,
+          spy_complete: false,
           bl_tm_ts: vec![(0, 0)].into_iter().collect(),
           bl_tm_pal: vec![(0, 0), (1, 2), (2, 2), (3, 2), (4, 2)]
             .into_iter()
@@ -472,6 +808,8 @@ pub fn init_game_state() -> GameState {
         SceneMap {
           scene: 3,
           both_complete: false,
+          bl_complete: false,
+          spy_complete: false,
           bl_tm_ts: vec![(0, 0)].into_iter().collect(),
           bl_tm_pal: vec![(0, 0), (1, 2), (2, 2), (3, 2), (4, 2)]
             .into_iter()
@@ -492,6 +830,8 @@ pub fn init_game_state() -> GameState {
         SceneMap {
           scene: 4,
           both_complete: false,
+          bl_complete: false,
+          spy_complete: false,
           bl_tm_ts: vec![(0, 0)].into_iter().collect(),
           bl_tm_pal: vec![(0, 0)].into_iter().collect(),
           bl_sp_ts: vec![].into_iter().collect(),
@@ -525,14 +865,68 @@ pub fn init_game_state() -> GameState {
         },
       ),
       // no map needed for victory
+      (
+        String::from("options"),
+        SceneMap {
+          scene: 9,
+          both_complete: false,
+          bl_complete: false,
+          spy_complete: false,
+          bl_tm_ts: vec![(0, 0)].into_iter().collect(),
+          bl_tm_pal: vec![(0, 0)].into_iter().collect(),
+          bl_sp_ts: vec![(0, 2)].into_iter().collect(),
+          bl_sp_pal: vec![(0, 2)].into_iter().collect(),
+
+          spy_tm_ts: vec![(0, 1)].into_iter().collect(),
+          spy_tm_pal: vec![(0, 1)].into_iter().collect(),
+          spy_sp_ts: vec![(0, 2)].into_iter().collect(),
+          spy_sp_pal: vec![(0, 2)].into_iter().collect(),
+        },
+      ),
+      (
+        String::from("controls"),
+        SceneMap {
+          scene: 10,
+          both_complete: false,
+          bl_complete: false,
+          spy_complete: false,
+          bl_tm_ts: vec![(0, 0)].into_iter().collect(),
+          bl_tm_pal: vec![(0, 0)].into_iter().collect(),
+          bl_sp_ts: vec![(0, 2)].into_iter().collect(),
+          bl_sp_pal: vec![(0, 2)].into_iter().collect(),
+
+          spy_tm_ts: vec![(0, 1)].into_iter().collect(),
+          spy_tm_pal: vec![(0, 1)].into_iter().collect(),
+          spy_sp_ts: vec![(0, 2)].into_iter().collect(),
+          spy_sp_pal: vec![(0, 2)].into_iter().collect(),
+        },
+      ),
     ]
     .into_iter()
     .collect(),
+    campaign: Campaign {
+      events: vec![
+        String::from("CROSS-COUNTRY BIATHLON"),
+        String::from("DOWNHILL BIATHLON"),
+        String::from("CRAGGY BIATHLON"),
+      ],
+      unlock_rule: UnlockRule::EitherMode,
+    },
+    event_markers: vec![],
+    settings: Settings::default(),
+    settings_changed: false,
     world,
     buglympics,
     spyder,
     music_tracker,
+    sfx_tracker: MusicTracker::new(0),
+    song_player: SongPlayer::new(),
+    sound_effects: SoundEffects::default(),
     player: Player::new(1, [0, 0]), // is not rendered till init is called
+    stats: Stats::default(),
+    lifetime_stats: Stats::default(),
+    event_stats: Stats::default(),
+    achievements: Achievements::default(),
     menu: Menu {
       name: String::from("MainMenu"),
       screen_x: 0,
@@ -548,13 +942,13 @@ pub fn init_game_state() -> GameState {
       cursor_tile_id: 10,
       cursor_sprite_id: 0,
       cursor_offset: [-16, 0],
-      input_time: 0,
-      input_threshold: 30,
+      ..Menu::default()
     },
     sfx_queue: vec![],
     spyder_shots: vec![],
     bl_shots: vec![],
-    inputs: HashSet::new()
+    inputs: HashSet::new(),
+    input_state: InputState::default()
   };
 
   return state;
@@ -565,3 +959,55 @@ pub trait SceneAction {
   fn init(&mut self, bus: &mut LentSysBus, state: &mut GameState);
   fn update(&mut self, bus: &mut LentSysBus, state: &mut GameState) -> (Vec<u8>, Vec<f32>);
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn record(time_remaining: f32, time_limit: f32, shots_hit: u32) -> SpyderEventRecord {
+    SpyderEventRecord {
+      event: String::from("crosscountry"),
+      success: true,
+      time_remaining,
+      time_limit,
+      targets_hit: 4,
+      targets_total: 4,
+      shots_fired: 4,
+      shots_hit,
+    }
+  }
+
+  #[test]
+  fn stars_for_targets_time_and_accuracy() {
+    assert_eq!(record(60.0, 120.0, 3).stars(), 3);
+    assert_eq!(record(59.0, 120.0, 3).stars(), 2);
+    assert_eq!(record(59.0, 120.0, 2).stars(), 1);
+  }
+
+  #[test]
+  fn no_stars_for_a_failed_or_unfinished_mission() {
+    let mut failed = record(60.0, 120.0, 4);
+    failed.success = false;
+    assert_eq!(failed.stars(), 0);
+
+    let mut missed = record(60.0, 120.0, 4);
+    missed.targets_hit = 3;
+    assert_eq!(missed.stars(), 0);
+  }
+
+  #[test]
+  fn stars_without_a_time_limit_count_the_clock_as_full() {
+    let untimed = record(0.0, 0.0, 4);
+    assert_eq!(untimed.time_left(), 1.0);
+    assert_eq!(untimed.stars(), 3);
+    assert!(untimed.rating().is_finite());
+  }
+
+  #[test]
+  fn no_shots_fired_is_no_accuracy_star() {
+    let mut record = record(60.0, 120.0, 0);
+    record.shots_fired = 0;
+    assert_eq!(record.accuracy(), 0.0);
+    assert_eq!(record.stars(), 2);
+  }
+}
@@ -0,0 +1,90 @@
+use crate::game::save::SaveData;
+
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+  pub distance: f32,
+  pub jumps: u32,
+  pub airtime: u32,
+  pub shots_fired: u32,
+  pub shots_hit: u32,
+  pub swaps: u32,
+  pub falls: u32,
+  pub bl_frames: u32,
+  pub spy_frames: u32,
+}
+
+impl Stats {
+  pub fn combined(&self, other: &Stats) -> Stats {
+    Stats {
+      distance: self.distance + other.distance,
+      jumps: self.jumps + other.jumps,
+      airtime: self.airtime + other.airtime,
+      shots_fired: self.shots_fired + other.shots_fired,
+      shots_hit: self.shots_hit + other.shots_hit,
+      swaps: self.swaps + other.swaps,
+      falls: self.falls + other.falls,
+      bl_frames: self.bl_frames + other.bl_frames,
+      spy_frames: self.spy_frames + other.spy_frames,
+    }
+  }
+
+  pub fn read(save: &SaveData, prefix: &str) -> Stats {
+    Stats {
+      distance: save.get_f32(&format!("{}.distance", prefix), 0.0),
+      jumps: save.get_u32(&format!("{}.jumps", prefix), 0),
+      airtime: save.get_u32(&format!("{}.airtime", prefix), 0),
+      shots_fired: save.get_u32(&format!("{}.shots_fired", prefix), 0),
+      shots_hit: save.get_u32(&format!("{}.shots_hit", prefix), 0),
+      swaps: save.get_u32(&format!("{}.swaps", prefix), 0),
+      falls: save.get_u32(&format!("{}.falls", prefix), 0),
+      bl_frames: save.get_u32(&format!("{}.bl_frames", prefix), 0),
+      spy_frames: save.get_u32(&format!("{}.spy_frames", prefix), 0),
+    }
+  }
+
+  pub fn write(&self, save: &mut SaveData, prefix: &str) {
+    save.set(&format!("{}.distance", prefix), self.distance);
+    save.set(&format!("{}.jumps", prefix), self.jumps);
+    save.set(&format!("{}.airtime", prefix), self.airtime);
+    save.set(&format!("{}.shots_fired", prefix), self.shots_fired);
+    save.set(&format!("{}.shots_hit", prefix), self.shots_hit);
+    save.set(&format!("{}.swaps", prefix), self.swaps);
+    save.set(&format!("{}.falls", prefix), self.falls);
+    save.set(&format!("{}.bl_frames", prefix), self.bl_frames);
+    save.set(&format!("{}.spy_frames", prefix), self.spy_frames);
+  }
+}
+
+// frames to M:SS, assuming 60 frames a second
+fn clock(frames: u32) -> String {
+  let seconds = frames / 60;
+  format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Session and lifetime side by side
+pub fn stats_table(session: &Stats, lifetime: &Stats) -> String {
+  let rows = vec![
+    (
+      "DISTANCE",
+      format!("{}M", (session.distance / 16.0) as u32),
+      format!("{}M", (lifetime.distance / 16.0) as u32),
+    ),
+    ("JUMPS", session.jumps.to_string(), lifetime.jumps.to_string()),
+    ("AIRTIME", clock(session.airtime), clock(lifetime.airtime)),
+    (
+      "SHOTS HIT",
+      format!("{}/{}", session.shots_hit, session.shots_fired),
+      format!("{}/{}", lifetime.shots_hit, lifetime.shots_fired),
+    ),
+    ("SWAPS", session.swaps.to_string(), lifetime.swaps.to_string()),
+    ("FALLS", session.falls.to_string(), lifetime.falls.to_string()),
+    ("BUGLYMPICS", clock(session.bl_frames), clock(lifetime.bl_frames)),
+    ("SPYDER", clock(session.spy_frames), clock(lifetime.spy_frames)),
+  ];
+
+  let mut table = format!("{:<11}{:>9}{:>10}\n", "", "SESSION", "TOTAL");
+  for (name, now, total) in rows.iter() {
+    table += &format!("{:<11}{:>9}{:>10}\n", name, now, total);
+  }
+  table
+}
@@ -0,0 +1,179 @@
+pub struct Tricks {
+  pub airborne: bool,
+  pub airtime: u32,
+  pub takeoff_y: f32,
+  pub peak_y: f32,
+  pub rotation: f32,
+  pub score: u32,
+  pub popup: String,
+  pub popup_time: u32,
+  pub big_air_time: u32,
+  pub spin_rate: f32,
+  pub landing_tolerance: f32,
+}
+
+impl Default for Tricks {
+  fn default() -> Self {
+    Self {
+      airborne: false,
+      airtime: 0,
+      takeoff_y: 0.0,
+      peak_y: 0.0,
+      rotation: 0.0,
+      score: 0,
+      popup: String::from(""),
+      popup_time: 0,
+      big_air_time: 30,
+      spin_rate: 15.0,
+      landing_tolerance: 30.0,
+    }
+  }
+}
+
+impl Tricks {
+  pub fn take_off(&mut self, scene_y: f32) {
+    self.airborne = true;
+    self.airtime = 0;
+    self.takeoff_y = scene_y;
+    self.peak_y = scene_y;
+    self.rotation = 0.0;
+  }
+
+  // spin runs from -1.0 for a full speed back flip to 1.0 for a front flip
+  pub fn update_air(&mut self, scene_y: f32, spin: f32) {
+    self.airtime += 1;
+    self.peak_y = self.peak_y.min(scene_y);
+    self.rotation += spin * self.spin_rate;
+  }
+
+  // Rotation left over from any full flips, from -180 to 180 degrees
+  pub fn tilt(&self) -> f32 {
+    let tilt = self.rotation % 360.0;
+    if tilt > 180.0 {
+      tilt - 360.0
+    } else if tilt < -180.0 {
+      tilt + 360.0
+    } else {
+      tilt
+    }
+  }
+
+  pub fn height(&self) -> f32 {
+    self.takeoff_y - self.peak_y
+  }
+
+  // Score the jump on touch down. `surface_angle` is the slope under the
+  // player in degrees, positive when it runs downhill in front of them.
+  // Returns None for a wipeout.
+  pub fn land(&mut self, surface_angle: f32) -> Option<u32> {
+    self.airborne = false;
+
+    let flips = (self.rotation.abs() / 360.0 + 0.5).floor() as u32;
+    let matched = (self.tilt() - surface_angle).abs() <= self.landing_tolerance;
+
+    // no spin means the skis just follow the ground
+    if !matched && self.rotation != 0.0 {
+      self.show(String::from("WIPEOUT"));
+      return None;
+    }
+
+    let mut points = 0;
+    let mut lines = vec![];
+
+    if self.airtime >= self.big_air_time {
+      let air_points = self.airtime * 2 + (self.height().max(0.0) / 4.0) as u32;
+      lines.push(format!("BIG AIR {}", air_points));
+      points += air_points;
+    }
+
+    if flips > 0 {
+      let flip_points = flips * 100;
+      lines.push(format!("FLIP X{} {}", flips, flip_points));
+      points += flip_points;
+    }
+
+    if points > 0 && matched && surface_angle != 0.0 {
+      lines.push(String::from("CLEAN +50"));
+      points += 50;
+    }
+
+    if points > 0 {
+      self.score += points;
+      self.show(lines.join("\n"));
+    }
+
+    Some(points)
+  }
+
+  pub fn show(&mut self, text: String) {
+    self.popup = text;
+    self.popup_time = 90;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn jump(frames: u32, spin: f32) -> Tricks {
+    let mut tricks = Tricks::default();
+    tricks.take_off(100.0);
+    for frame in 0..frames {
+      tricks.update_air(100.0 - frame as f32, spin);
+    }
+    tricks
+  }
+
+  #[test]
+  fn small_hop_scores_nothing() {
+    let mut tricks = jump(10, 0.0);
+    assert_eq!(tricks.land(0.0), Some(0));
+    assert_eq!(tricks.score, 0);
+    assert!(!tricks.airborne);
+  }
+
+  #[test]
+  fn big_air_scores_airtime_and_height() {
+    // 30 frames climbing to 29 above the takeoff
+    let mut tricks = jump(30, 0.0);
+    assert_eq!(tricks.land(0.0), Some(30 * 2 + 7));
+    assert_eq!(tricks.popup, "BIG AIR 67");
+  }
+
+  #[test]
+  fn flip_landed_on_a_slope_is_clean() {
+    // 24 frames at full spin is one front flip
+    let mut tricks = jump(24, 1.0);
+    assert_eq!(tricks.tilt(), 0.0);
+    assert_eq!(tricks.land(10.0), Some(100 + 50));
+    assert_eq!(tricks.score, 150);
+    assert_eq!(tricks.popup, "FLIP X1 100\nCLEAN +50");
+  }
+
+  #[test]
+  fn back_flips_count_too() {
+    // long enough for big air as well, 47 above the takeoff
+    let mut tricks = jump(48, -1.0);
+    assert_eq!(tricks.land(0.0), Some(48 * 2 + 11 + 200));
+    assert_eq!(tricks.popup, "BIG AIR 107\nFLIP X2 200");
+  }
+
+  #[test]
+  fn landing_upside_down_wipes_out() {
+    let mut tricks = jump(12, 1.0);
+    assert_eq!(tricks.land(0.0), None);
+    assert_eq!(tricks.score, 0);
+    assert_eq!(tricks.popup, "WIPEOUT");
+  }
+
+  #[test]
+  fn tilt_wraps_to_half_a_turn_either_way() {
+    let mut tricks = Tricks {
+      rotation: 270.0,
+      ..Default::default()
+    };
+    assert_eq!(tricks.tilt(), -90.0);
+    tricks.rotation = -450.0;
+    assert_eq!(tricks.tilt(), -90.0);
+  }
+}
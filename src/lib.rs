@@ -5,12 +5,16 @@ use lentsys::apu::{APUConfig, APU, render_audio};
 use lentsys::apu::music::AudioSource;
 use lentsys::control::PadControl;
 use lentsys::game_pak::{GamePak};
+use std::collections::HashSet;
 use wasm_bindgen::prelude::*;
 extern crate console_error_panic_hook;
 
 mod game;
 mod scenes;
-use crate::game::state::{init_game_state, GameState};
+use crate::game::save::SaveData;
+use crate::game::songs::{self, MAX_LAYERS};
+use crate::game::sounds::EFFECT_SYNTHS;
+use crate::game::state::{init_game_state, GameState, PlayMode};
 
 #[wasm_bindgen]
 extern "C" {
@@ -48,7 +52,7 @@ impl BlSpy {
           apu: APU::new(
              APUConfig {
               sample_rate: 44100.0,
-              max_synths: 8,
+              max_synths: EFFECT_SYNTHS + MAX_LAYERS * songs::CHANNELS,
               max_samples: 8,
              }
           ),
@@ -64,6 +68,7 @@ impl BlSpy {
 
   pub fn update(&mut self) {
     let mut won_all = true;
+    self.state.input_state.update(&self.state.inputs, &self.bus.controllers[0]);
 
     // If the current scene is not RUNNING, it is most likely INITIAL or COMPLETE.
     // Load next scene.
@@ -78,8 +83,13 @@ impl BlSpy {
         }
       }
       
+      // Spyder needs enough stars as well
+      if self.state.spyder.total_stars() < self.state.spyder.stars_required {
+        won_all = false;
+      }
+
       // it's over!
-      if won_all {
+      if won_all && self.state.play_mode == PlayMode::Campaign {
         self.state.current_scene = 8;
       }
 
@@ -107,6 +117,15 @@ impl BlSpy {
         8 => {
           scenes::victory::update(&mut self.bus, &mut self.state);
         },
+        9 => {
+          scenes::options::update(&mut self.bus, &mut self.state);
+        },
+        10 => {
+          scenes::controls::update(&mut self.bus, &mut self.state);
+        },
+        11 => {
+          scenes::name_entry::update(&mut self.bus, &mut self.state);
+        },
         5.. => {
           scenes::biathlon::update(&mut self.bus, &mut self.state);
         },
@@ -116,6 +135,8 @@ impl BlSpy {
       }
     }
 
+    self.state.achievements.update_toast(&mut self.bus);
+
   }
 
   fn load_scene(&mut self) {
@@ -128,6 +149,9 @@ impl BlSpy {
       &mut self.bus.game_pak.assets,
     );
 
+    // tile maps were replaced, toasts need a new one
+    self.state.achievements.reserve_toast(&mut self.bus);
+
     // scene state should now be set to RUNNING
     match self.state.current_scene {
       0 => {
@@ -148,6 +172,15 @@ impl BlSpy {
       8 => {
         scenes::victory::init(&mut self.bus, &mut self.state);
       }
+      9 => {
+        scenes::options::init(&mut self.bus, &mut self.state);
+      }
+      10 => {
+        scenes::controls::init(&mut self.bus, &mut self.state);
+      }
+      11 => {
+        scenes::name_entry::init(&mut self.bus, &mut self.state);
+      }
       5.. => {
         scenes::biathlon::init(&mut self.bus, &mut self.state);
       },
@@ -156,6 +189,11 @@ impl BlSpy {
       }
     }
 
+    // the song data says which songs each scene plays and how
+    let focus = songs::mode_layer(self.state.game);
+    let scene = self.state.current_scene;
+    self.state.song_player.play_scene(&mut self.bus, scene, focus);
+
   }
 
   pub fn get_image_data(&self) -> *const u8 {
@@ -181,23 +219,121 @@ impl BlSpy {
 
   pub fn render_audio(&mut self, time_delta: f32){
     let samples = self.bus.apu.config.sample_rate * time_delta;
+    let settings = &self.state.settings;
 
-    // bg music sample
-    if self.bus.apu.samples.len() > 0 {
-      self.state.sfx_queue.push(
+    let music_level = settings.music_level();
+    let master_level = settings.master_level();
+    game::sounds::set_effect_volume(
+      &mut self.bus,
+      game::sounds::EFFECT_VOLUME * settings.sfx_level(),
+    );
+
+    // bg music sample, mixed on its own as samples have no volume to set
+    let mut music_queue = vec![];
+    if self.bus.apu.samples.len() > 0 && music_level > 0.0 {
+      music_queue.push(
         (0.0, AudioSource::Sample, 0, samples as usize)
       );
     }
+
+    let music_data = render_audio(
+      time_delta,
+      &mut self.bus.apu.music,
+      &mut self.bus.apu.synths,
+      &mut self.bus.apu.samples,
+      &mut self.state.music_tracker,
+      &mut music_queue,
+      &self.bus.apu.config
+    );
+
+    self.state.sound_effects.render(
+      &mut self.bus,
+      samples as usize,
+      &mut self.state.sfx_queue,
+    );
+
+    // tracker music
+    if music_level > 0.0 {
+      self.state.song_player.render(
+        &mut self.bus,
+        time_delta,
+        music_level,
+        samples as usize,
+        &mut self.state.sfx_queue,
+      );
+    }
     
     self.audio_data = render_audio(
       time_delta,
       &mut self.bus.apu.music,
       &mut self.bus.apu.synths,
       &mut self.bus.apu.samples,
-      &mut self.state.music_tracker,
+      &mut self.state.sfx_tracker,
       &mut self.state.sfx_queue,
       &self.bus.apu.config
     );
+
+    for (idx, sample) in self.audio_data.iter_mut().enumerate() {
+      let music = music_data.get(idx).unwrap_or(&0.0) * music_level;
+      *sample = (*sample + music) * master_level;
+    }
+  }
+
+  pub fn get_save_data(&self) -> String {
+    self.state.save_data().serialize()
+  }
+
+  pub fn set_save_data(&mut self, data: &str) {
+    self.state.load_save_data(&SaveData::parse(data));
+  }
+
+  // sound effect definitions packed from assets/effects.sfx, returns a
+  // line for each part that doesn't match the game's effects
+  pub fn set_effect_data(&mut self, data: &str) -> String {
+    self.state.sound_effects.load(data).join("\n")
+  }
+
+  // tracker songs packed from the DefleMask modules, and the scenes that
+  // play them from assets/scene_music.cfg
+  pub fn set_song_data(&mut self, data: &str) {
+    self.state.song_player.load(data);
+  }
+
+  // true once after the options close, when the config should be saved
+  pub fn take_settings_changed(&mut self) -> bool {
+    std::mem::replace(&mut self.state.settings_changed, false)
+  }
+
+  pub fn get_config_data(&self) -> String {
+    self.state.config_data().serialize()
+  }
+
+  pub fn set_config_data(&mut self, data: &str) {
+    self.state.load_config_data(&SaveData::parse(data));
+  }
+
+  pub fn get_window_scale(&self) -> u32 {
+    self.state.settings.window_scale as u32
+  }
+
+  pub fn get_fullscreen(&self) -> bool {
+    self.state.settings.fullscreen
+  }
+
+  pub fn get_dead_zone(&self) -> f32 {
+    self.state.settings.dead_zone as f32 / 100.0
+  }
+
+  // Bindings as config lines, e.g. "keys.up=Up,W", for the web keyMap
+  pub fn get_key_bindings(&self) -> String {
+    let mut bindings = SaveData::default();
+    self.state.settings.key_bindings.write(&mut bindings);
+    bindings.serialize()
+  }
+
+  // Hosts pass every fresh key press here for the rebinding screen
+  pub fn capture_key(&mut self, key: &str) {
+    self.state.captured_key = Some(key.to_string());
   }
 
   pub fn set_inputs(&mut self, controller: &PadControl){
@@ -209,6 +345,7 @@ impl BlSpy {
 pub trait Native {
   fn get_image(&self) -> &Vec<u8>;
   fn get_audio(&self) -> &Vec<f32>;
+  fn keys_to_pad(&self, keys: &HashSet<String>, controller: &mut PadControl);
 }
 
 impl Native for BlSpy {
@@ -219,4 +356,8 @@ impl Native for BlSpy {
   fn get_audio(&self) -> &Vec<f32> {
     &self.audio_data
   }
+
+  fn keys_to_pad(&self, keys: &HashSet<String>, controller: &mut PadControl) {
+    self.state.settings.key_bindings.to_pad(keys, controller);
+  }
 }
\ No newline at end of file
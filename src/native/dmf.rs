@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use flate2::read::ZlibDecoder;
+
+// DefleMask module import, version 24 files for the 8-bit systems. The
+// instruments' macros and the first four channels are kept, which covers
+// the pulses, triangle and noise, and written out in the game's song format.
+
+const FORMAT_VERSION: u8 = 24;
+// channels the game's song player has
+const SONG_CHANNELS: usize = 4;
+// DMF stores arpeggio macros offset so 12 is no change
+const ARPEGGIO_OFFSET: i32 = 12;
+const NOTE_OFF: i16 = 100;
+
+// (system id, channels, voices for the kept channels) for the systems with
+// 8-bit style instruments. The Game Boy's wave channel plays as a triangle.
+const SYSTEMS: [(u8, usize, [&str; SONG_CHANNELS]); 3] = [
+  (0x03, 4, ["pulse", "pulse", "pulse", "noise"]),
+  (0x04, 4, ["pulse", "pulse", "triangle", "noise"]),
+  (0x06, 5, ["pulse", "pulse", "triangle", "noise"]),
+];
+const SYSTEM_GAMEBOY: u8 = 0x04;
+const MAX_VOLUME: i32 = 15;
+
+#[derive(Debug, Default)]
+pub struct Macro {
+  pub values: Vec<i32>,
+  pub loop_at: Option<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct DmfInstrument {
+  pub volume: Macro,
+  pub arpeggio: Macro,
+  pub duty: Macro,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DmfCell {
+  pub note: i16,
+  pub octave: i16,
+  pub volume: i16,
+  pub instrument: i16,
+}
+
+#[derive(Debug)]
+pub struct DmfModule {
+  // waveform each kept channel plays with
+  pub voices: [&'static str; SONG_CHANNELS],
+  pub time_base: u8,
+  pub ticks: [u8; 2],
+  pub hz: f32,
+  pub rows: usize,
+  // pattern index per channel and matrix row
+  pub matrix: Vec<Vec<u8>>,
+  // pattern cells per channel and matrix row
+  pub patterns: Vec<Vec<Vec<DmfCell>>>,
+  pub instruments: Vec<DmfInstrument>,
+}
+
+struct Reader {
+  data: Vec<u8>,
+  pos: usize,
+}
+
+impl Reader {
+  fn bytes(&mut self, len: usize) -> Result<&[u8], String> {
+    if self.pos + len > self.data.len() {
+      return Err(format!("DMF ended early at byte {}", self.pos));
+    }
+    self.pos += len;
+    Ok(&self.data[self.pos - len..self.pos])
+  }
+
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.bytes(1)?[0])
+  }
+
+  fn i16(&mut self) -> Result<i16, String> {
+    let bytes = self.bytes(2)?;
+    Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+  }
+
+  fn i32(&mut self) -> Result<i32, String> {
+    let bytes = self.bytes(4)?;
+    Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    let len = self.u8()? as usize;
+    Ok(String::from_utf8_lossy(self.bytes(len)?).to_string())
+  }
+
+  fn macro_values(&mut self) -> Result<Macro, String> {
+    let len = self.u8()? as usize;
+    let mut values = vec![];
+    for _ in 0..len {
+      values.push(self.i32()?);
+    }
+
+    // only stored when there are values, 255 for no loop
+    let loop_at = if len > 0 { Some(self.u8()?) } else { None };
+    Ok(Macro {
+      values,
+      loop_at: loop_at.filter(|idx| (*idx as usize) < len),
+    })
+  }
+}
+
+pub fn read_module(path: &str) -> Result<DmfModule, String> {
+  let mut compressed = vec![];
+  File::open(path)
+    .and_then(|mut file| file.read_to_end(&mut compressed))
+    .map_err(|err| format!("{}: {}", path, err))?;
+
+  let mut data = vec![];
+  ZlibDecoder::new(&compressed[..])
+    .read_to_end(&mut data)
+    .map_err(|err| format!("{}: {}", path, err))?;
+
+  let mut reader = Reader { data, pos: 0 };
+  if reader.bytes(16)? != b".DelekDefleMask." {
+    return Err(format!("{}: not a DefleMask module", path));
+  }
+
+  let version = reader.u8()?;
+  if version != FORMAT_VERSION {
+    return Err(format!("{}: unsupported DMF version {}", path, version));
+  }
+
+  let system = reader.u8()?;
+  let (channels, voices) = match SYSTEMS.iter().find(|(id, _, _)| *id == system) {
+    Some((_, channels, voices)) => (*channels, *voices),
+    None => return Err(format!("{}: unsupported system {:#x}", path, system)),
+  };
+
+  // song name, author and highlights
+  reader.string()?;
+  reader.string()?;
+  reader.bytes(2)?;
+
+  let time_base = reader.u8()?;
+  let ticks = [reader.u8()?, reader.u8()?];
+  let ntsc = reader.u8()? == 1;
+  let custom_hz = reader.u8()? == 1;
+  let hz_text = String::from_utf8_lossy(reader.bytes(3)?).to_string();
+  let hz = if custom_hz {
+    hz_text.trim_end_matches('\0').parse::<f32>().unwrap_or(60.0)
+  } else if ntsc {
+    60.0
+  } else {
+    50.0
+  };
+
+  let rows = reader.i32()?.max(1) as usize;
+  let matrix_rows = reader.u8()? as usize;
+  let mut matrix = vec![];
+  for _ in 0..channels {
+    matrix.push(reader.bytes(matrix_rows)?.to_vec());
+  }
+
+  let mut instruments = vec![];
+  for _ in 0..reader.u8()? {
+    let name = reader.string()?;
+    if reader.u8()? != 0 {
+      return Err(format!("{}: FM instrument {} is not supported", path, name));
+    }
+
+    // the Game Boy has a hardware envelope in place of a volume macro
+    let mut volume = if system == SYSTEM_GAMEBOY {
+      Macro::default()
+    } else {
+      reader.macro_values()?
+    };
+    let arpeggio = reader.macro_values()?;
+    // arpeggio mode, only normal is played
+    reader.u8()?;
+    let duty = reader.macro_values()?;
+    // wavetable
+    reader.macro_values()?;
+
+    // Game Boy envelope volume, direction and length, then sound length
+    if system == SYSTEM_GAMEBOY {
+      let envelope = reader.bytes(4)?;
+      volume = envelope_macro(envelope[0], envelope[1], envelope[2]);
+    }
+
+    instruments.push(DmfInstrument {
+      volume,
+      arpeggio: Macro {
+        values: arpeggio.values.iter().map(|value| value - ARPEGGIO_OFFSET).collect(),
+        loop_at: arpeggio.loop_at,
+      },
+      duty,
+    });
+  }
+
+  // wavetables
+  for _ in 0..reader.u8()? {
+    let len = reader.i32()?.max(0) as usize;
+    reader.bytes(len * 4)?;
+  }
+
+  let mut patterns = vec![];
+  for _ in 0..channels {
+    let effect_columns = reader.u8()? as usize;
+    let mut channel_patterns = vec![];
+    for _ in 0..matrix_rows {
+      let mut cells = vec![];
+      for _ in 0..rows {
+        let note = reader.i16()?;
+        let octave = reader.i16()?;
+        let volume = reader.i16()?;
+        // effects aren't played
+        reader.bytes(effect_columns * 4)?;
+        let instrument = reader.i16()?;
+        cells.push(DmfCell {
+          note,
+          octave,
+          volume,
+          instrument,
+        });
+      }
+      channel_patterns.push(cells);
+    }
+    patterns.push(channel_patterns);
+  }
+
+  Ok(DmfModule {
+    voices,
+    time_base,
+    ticks,
+    hz,
+    rows,
+    matrix,
+    patterns,
+    instruments,
+  })
+}
+
+// Game Boy envelopes step the volume up or down once every `length` ticks,
+// or hold it when the length is 0
+fn envelope_macro(volume: u8, direction: u8, length: u8) -> Macro {
+  let mut volume = (volume as i32).min(MAX_VOLUME);
+  let step = if direction == 0 { -1 } else { 1 };
+  let mut values = vec![volume];
+  if length > 0 {
+    while volume > 0 && volume < MAX_VOLUME {
+      volume += step;
+      values.extend(std::iter::repeat(volume).take(length as usize));
+    }
+  }
+
+  Macro {
+    values,
+    loop_at: None,
+  }
+}
+
+impl DmfCell {
+  fn is_empty(&self) -> bool {
+    self.note == 0 && self.octave == 0 && self.volume < 0 && self.instrument < 0
+  }
+
+  // semitones above C0, DMF counts C# as 1 up to C of the next octave as 12
+  fn pitch(&self) -> String {
+    match self.note {
+      NOTE_OFF => String::from("off"),
+      1..=12 => (self.octave as i32 * 12 + self.note as i32).to_string(),
+      _ => String::from("-"),
+    }
+  }
+}
+
+fn optional(value: i16) -> String {
+  if value < 0 {
+    String::from("-")
+  } else {
+    value.to_string()
+  }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+  values
+    .iter()
+    .map(|value| value.to_string())
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
+impl DmfModule {
+  // `key=value` lines in the song format read by the game's song player
+  pub fn to_song_data(&self, name: &str) -> String {
+    let mut lines = vec![];
+    let speed = self.time_base as usize + 1;
+    lines.push(format!(
+      "{}.ticks={},{}",
+      name,
+      self.ticks[0] as usize * speed,
+      self.ticks[1] as usize * speed
+    ));
+    lines.push(format!("{}.hz={}", name, self.hz));
+    lines.push(format!("{}.rows={}", name, self.rows));
+    lines.push(format!("{}.voices={}", name, self.voices.join(",")));
+
+    for (channel, steps) in self.matrix.iter().take(SONG_CHANNELS).enumerate() {
+      lines.push(format!("{}.order.{}={}", name, channel, join(steps)));
+
+      // the matrix repeats a pattern's cells wherever it's used
+      let mut written = vec![];
+      for (step, idx) in steps.iter().enumerate() {
+        if written.contains(idx) {
+          continue;
+        }
+        written.push(*idx);
+
+        let cells: Vec<String> = self.patterns[channel][step]
+          .iter()
+          .enumerate()
+          .filter(|(_, cell)| !cell.is_empty())
+          .map(|(row, cell)| {
+            format!(
+              "{}:{}:{}:{}",
+              row,
+              cell.pitch(),
+              optional(cell.volume),
+              optional(cell.instrument)
+            )
+          })
+          .collect();
+        lines.push(format!("{}.pattern.{}.{}={}", name, channel, idx, cells.join(";")));
+      }
+    }
+
+    lines.push(format!("{}.instruments={}", name, self.instruments.len()));
+    for (idx, instrument) in self.instruments.iter().enumerate() {
+      let macros = [
+        ("volume", &instrument.volume),
+        ("arpeggio", &instrument.arpeggio),
+        ("duty", &instrument.duty),
+      ];
+      for (field, values) in macros.iter() {
+        let key = format!("{}.instrument.{}.{}", name, idx, field);
+        lines.push(format!("{}={}", key, join(&values.values)));
+        if let Some(loop_at) = values.loop_at {
+          lines.push(format!("{}_loop={}", key, loop_at));
+        }
+      }
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const THEME: &str = "./assets/event_select_theme.dmf";
+
+  #[test]
+  fn reads_the_event_select_theme() {
+    let module = read_module(THEME).expect("theme should load");
+    assert_eq!(module.voices, ["pulse", "pulse", "triangle", "noise"]);
+    assert_eq!(module.ticks, [3, 3]);
+    assert_eq!(module.hz, 60.0);
+    assert_eq!(module.rows, 64);
+    // an NES module, the DPCM channel is read but not played
+    assert_eq!(module.matrix.len(), 5);
+    assert_eq!(module.matrix[0], vec![0, 3, 0, 3, 3]);
+    assert_eq!(module.patterns.len(), 5);
+    assert_eq!(module.patterns[0][0].len(), 64);
+  }
+
+  #[test]
+  fn reads_instrument_macros() {
+    let module = read_module(THEME).expect("theme should load");
+    assert_eq!(module.instruments.len(), 3);
+
+    let lead = &module.instruments[1];
+    assert_eq!(lead.volume.values[..4], [15, 14, 13, 12]);
+    assert_eq!(lead.arpeggio.values, vec![0, -5, 0]);
+    assert_eq!(lead.arpeggio.loop_at, Some(1));
+    assert_eq!(lead.duty.values, vec![2, 3, 1]);
+    assert_eq!(lead.duty.loop_at, Some(0));
+  }
+
+  #[test]
+  fn game_boy_envelopes_step_the_volume() {
+    assert_eq!(envelope_macro(3, 0, 2).values, vec![3, 2, 2, 1, 1, 0, 0]);
+    assert_eq!(envelope_macro(13, 1, 1).values, vec![13, 14, 15]);
+    assert_eq!(envelope_macro(9, 0, 0).values, vec![9]);
+  }
+
+  #[test]
+  fn song_data_has_the_player_keys() {
+    let module = read_module(THEME).expect("theme should load");
+    let data = module.to_song_data("theme");
+    for line in [
+      "theme.ticks=3,3",
+      "theme.hz=60",
+      "theme.rows=64",
+      "theme.voices=pulse,pulse,triangle,noise",
+      "theme.order.0=0,3,0,3,3",
+    ]
+    .iter()
+    {
+      assert!(data.lines().any(|data_line| data_line == *line), "missing {}", line);
+    }
+  }
+
+  #[test]
+  fn missing_module_is_an_error() {
+    assert!(read_module("./assets/no_such_song.dmf").is_err());
+  }
+}
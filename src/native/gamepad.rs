@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use lentsys::control::PadControl;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+
+const AXIS_MAX: f32 = 32767.0;
+
+pub struct Gamepads {
+  pub subsystem: GameControllerSubsystem,
+  pub controllers: HashMap<u32, GameController>,
+  // fraction of the stick's travel that is ignored
+  pub dead_zone: f32,
+}
+
+impl Gamepads {
+  pub fn new(sdl_context: &sdl2::Sdl, dead_zone: f32) -> Gamepads {
+    let subsystem = sdl_context.game_controller().unwrap();
+
+    // pads already plugged in also arrive as added events
+    Gamepads {
+      subsystem,
+      controllers: HashMap::new(),
+      dead_zone,
+    }
+  }
+
+  pub fn handle_event(&mut self, event: &Event) {
+    match event {
+      Event::ControllerDeviceAdded { which, .. } => {
+        match self.subsystem.open(*which) {
+          Ok(controller) => {
+            self.controllers.insert(controller.instance_id(), controller);
+          }
+          Err(e) => eprintln!("Failed to open controller: {}", e),
+        }
+      }
+      Event::ControllerDeviceRemoved { which, .. } => {
+        self.controllers.remove(which);
+      }
+      _ => {}
+    }
+  }
+
+  // Merge every connected pad on top of the keyboard input
+  pub fn to_pad(&self, pad: &mut PadControl) {
+    for controller in self.controllers.values() {
+      if controller.button(Button::DPadUp) {
+        pad.up = 255;
+      }
+
+      if controller.button(Button::DPadDown) {
+        pad.down = 255;
+      }
+
+      if controller.button(Button::DPadLeft) {
+        pad.left = 255;
+      }
+
+      if controller.button(Button::DPadRight) {
+        pad.right = 255;
+      }
+
+      if controller.button(Button::Start) {
+        pad.start = 255;
+      }
+
+      if controller.button(Button::A) {
+        pad.a = 255;
+      }
+
+      if controller.button(Button::Y) {
+        pad.b = 255;
+      }
+
+      if controller.button(Button::X) {
+        pad.x = 255;
+      }
+
+      if controller.button(Button::B) {
+        pad.y = 255;
+      }
+
+      let x = self.axis_value(controller.axis(Axis::LeftX));
+      let y = self.axis_value(controller.axis(Axis::LeftY));
+      press(&mut pad.left, -x);
+      press(&mut pad.right, x);
+      press(&mut pad.up, -y);
+      press(&mut pad.down, y);
+    }
+  }
+
+  // -255 to 255 once past the dead zone
+  fn axis_value(&self, raw: i16) -> i16 {
+    let amount = (raw as f32 / AXIS_MAX).max(-1.0).min(1.0);
+    if amount.abs() <= self.dead_zone {
+      return 0;
+    }
+
+    let scaled = (amount.abs() - self.dead_zone) / (1.0 - self.dead_zone);
+    (scaled * amount.signum() * 255.0) as i16
+  }
+}
+
+fn press(button: &mut u8, value: i16) {
+  if value > 0 {
+    *button = (*button).max(value as u8);
+  }
+}
@@ -1,7 +1,10 @@
 #[cfg(feature = "native")]
 extern crate sdl2;
 use sdl2::render::Texture;
+use sdl2::video::FullscreenType;
 
+pub mod dmf;
+pub mod gamepad;
 pub mod pack;
 
 pub struct NativeVideo {
@@ -15,7 +18,9 @@ impl NativeVideo {
     sdl_context: &sdl2::Sdl, 
     window_name: String, 
     width: u32, 
-    height: u32 
+    height: u32,
+    scale: u32,
+    fullscreen: bool,
   ) -> NativeVideo {
 
 
@@ -24,10 +29,9 @@ impl NativeVideo {
     let window = video_subsystem
         .window(
             &window_name,
-            width * 2,
-            height * 2,
+            width * scale,
+            height * scale,
         )
-        //.fullscreen()
         .position_centered()
         .build()
         .map_err(|e| e.to_string()).unwrap();
@@ -39,11 +43,31 @@ impl NativeVideo {
         .build()
         .unwrap();
 
-    NativeVideo {
+    let mut vid = NativeVideo {
       width,
       height,
       canvas
-    }    
+    };
+    vid.set_mode(scale, fullscreen);
+    vid
+  }
+
+  // the window manager can refuse a mode, which is left to the caller
+  pub fn set_mode(&mut self, scale: u32, fullscreen: bool) -> Result<(), String> {
+    let window = self.canvas.window_mut();
+    let fullscreen_type = if fullscreen {
+      FullscreenType::Desktop
+    } else {
+      FullscreenType::Off
+    };
+
+    window.set_fullscreen(fullscreen_type)?;
+    if !fullscreen {
+      window
+        .set_size(self.width * scale, self.height * scale)
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
   }
 
   pub fn render_frame(
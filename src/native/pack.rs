@@ -3,6 +3,16 @@ use lentsys::game_pak::scene::Scene;
 use lentsys::game_pak::GamePak;
 use lentsys::io::Prepare;
 
+use crate::native::dmf;
+
+// Tracker songs in ./assets/, either DefleMask modules or hand written
+// .song files in the game's own format
+const SONGS: [&str; 3] = [
+  "event_select_theme",
+  "biathlon_buglympics",
+  "biathlon_spyder",
+];
+
 #[cfg(feature="native")]
 pub fn pack_game(){
   // Prepare game asset binary from raw assets
@@ -51,7 +61,6 @@ pub fn pack_game(){
           String::from("ant_walk"),
         ],
         tile_map_names: vec![String::from("crosscountry")],
-        sample_names: vec![String::from("pachyderm")],
         ..Default::default()
       },
       Scene {
@@ -64,7 +73,6 @@ pub fn pack_game(){
           String::from("ant_walk"),
         ],
         tile_map_names: vec![String::from("downhill")],
-        sample_names: vec![String::from("pachyderm")],
         ..Default::default()
       },
       Scene {
@@ -77,7 +85,6 @@ pub fn pack_game(){
           String::from("ant_walk"),
         ],
         tile_map_names: vec![String::from("craggy")],
-        sample_names: vec![String::from("pachyderm")],
         ..Default::default()
       },
       Scene {
@@ -85,6 +92,24 @@ pub fn pack_game(){
         sample_names: vec![String::from("pachyderm")],
         ..Default::default()
       },
+      Scene {
+        name: String::from("options"),
+        tile_set_names: vec![String::from("nation_select"), String::from("tool_select")],
+        tile_map_names: vec![String::from("event_select")],
+        ..Default::default()
+      },
+      Scene {
+        name: String::from("controls"),
+        tile_set_names: vec![String::from("nation_select"), String::from("tool_select")],
+        tile_map_names: vec![String::from("event_select")],
+        ..Default::default()
+      },
+      Scene {
+        name: String::from("name_entry"),
+        tile_set_names: vec![String::from("nation_select"), String::from("tool_select")],
+        tile_map_names: vec![String::from("event_select")],
+        ..Default::default()
+      },
     ],
   };
 
@@ -94,4 +119,56 @@ pub fn pack_game(){
   gp.assets.prepare();
   gp.to_binary(&String::from("./buglympics.bin"));
   gp.to_binary(&String::from("./web/buglympics.bin"));
+
+  pack_songs();
+  pack_effects();
+}
+
+// Effect definitions are copied as they are, so they can be changed
+// without a rebuild
+#[cfg(feature="native")]
+pub fn pack_effects(){
+  let data = std::fs::read_to_string("./assets/effects.sfx").expect("Failed to read effects");
+  std::fs::write("./buglympics.sfx", &data).expect("Failed to write effects file");
+  std::fs::write("./web/buglympics.sfx", &data).expect("Failed to write effects file");
+}
+
+// Songs live beside the game pak as `key=value` lines, which the game reads
+// with set_song_data
+#[cfg(feature="native")]
+pub fn pack_songs(){
+  let mut data = format!("songs={}\n", SONGS.join(","));
+  for name in SONGS.iter() {
+    let dmf_path = format!("./assets/{}.dmf", name);
+    if std::path::Path::new(&dmf_path).exists() {
+      match dmf::read_module(&dmf_path) {
+        Ok(module) => data.push_str(&module.to_song_data(name)),
+        Err(err) => println!("Skipping song {}", err),
+      }
+      continue;
+    }
+
+    // .song files leave the song name off their keys
+    match std::fs::read_to_string(format!("./assets/{}.song", name)) {
+      Ok(song) => {
+        for line in song.lines().filter(|line| !line.starts_with('#') && line.contains('=')) {
+          data.push_str(&format!("{}.{}\n", name, line));
+        }
+      }
+      Err(err) => println!("Skipping song {}: {}", name, err),
+    }
+  }
+
+  // which songs each scene plays
+  match std::fs::read_to_string("./assets/scene_music.cfg") {
+    Ok(scenes) => {
+      for line in scenes.lines().filter(|line| !line.starts_with('#') && line.contains('=')) {
+        data.push_str(&format!("{}\n", line));
+      }
+    }
+    Err(err) => println!("Skipping scene music: {}", err),
+  }
+
+  std::fs::write("./buglympics.songs", &data).expect("Failed to write song file");
+  std::fs::write("./web/buglympics.songs", &data).expect("Failed to write song file");
 }
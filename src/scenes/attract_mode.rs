@@ -3,12 +3,10 @@ use lentsys::ui::text::TextBox;
 use lentsys::game_pak::scene::SceneState;
 use crate::game::cutscene::Shot;
 use crate::game::input::InputCode;
-use crate::game::state::GameMode;
 use crate::game::state::GameState;
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
   state.current_shot = 0;
-  state.input_cooldown = 15;
   state.bl_shots = vec![
     Shot {
       tile_map_name: String::from("cut_01_arrival"),
@@ -133,22 +131,14 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     },
   ];
 
-  match &state.game {
-    GameMode::Buglympics => {
-      state.bl_shots[state.current_shot].load(bus);
-    }
-    GameMode::Spyder => {
-      state.spyder_shots[state.current_shot].load(bus);
-    }
-  }
+  state.load_shot(bus);
 }
 
 pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
 
   // next shot, please
-  if state.inputs.contains(&InputCode::Confirm) && state.input_cooldown == 0 {
+  if state.input_state.pressed(InputCode::Confirm) {
     state.current_shot += 1;
-    state.input_cooldown = 15;
     // if this was the last shot, the scene is over
     // the length of both are the same.
     if state.current_shot > state.bl_shots.len() - 1 {
@@ -159,33 +149,13 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
       state.current_scene = 2;
     
     } else {
-      match &state.game {
-        GameMode::Buglympics => {
-          state.bl_shots[state.current_shot].load(bus);
-        }
-        GameMode::Spyder => {
-          state.spyder_shots[state.current_shot].load(bus);
-        }
-      }
+      state.load_shot(bus);
     }
   }
 
   //swap game
-  if state.inputs.contains(&InputCode::Swap) && state.swap_cooldown > 8 {
+  if state.input_state.pressed(InputCode::Swap) {
     state.swap_game(bus);
-    match &state.game {
-      GameMode::Buglympics => {
-        state.bl_shots[state.current_shot].load(bus);
-      }
-      GameMode::Spyder => {
-        state.spyder_shots[state.current_shot].load(bus);
-      }
-    }
+    state.load_shot(bus);
   }
-
-  if state.input_cooldown > 0 {
-    state.input_cooldown -= 1;
-  }
-
-  state.swap_cooldown += 1;
 }
@@ -4,11 +4,17 @@ use lentsys::ui::text::Text;
 use lentsys::ui::text::TextBox;
 use lentsys::game_pak::scene::SceneState;
 
+use crate::game::achievements::GameEvent;
 use crate::game::input::InputCode;
 use crate::game::player::Player;
 use crate::game::sounds::prepare_effects;
 use crate::game::state::BuglympicsEventRecord;
-use crate::game::state::GameState;
+use crate::game::state::SpyderEventRecord;
+use crate::game::state::TimeTrialRecord;
+use crate::game::state::{GameState, PlayMode};
+
+// seconds added to the Buglympics clock for falling off the course
+const RESPAWN_PENALTY: f32 = 5.0;
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     // set timers and end state
@@ -17,6 +23,42 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     state.spy_timer = state.spyder.events[&state.event].time_limit;
     state.bl_finished = false;
     state.spy_finished = false;
+    state.practice_point = 0;
+    state.event_stats = state.stats.clone();
+
+    // time trial only runs Buglympics
+    if state.play_mode == PlayMode::TimeTrial {
+        state.game = crate::game::state::GameMode::Buglympics;
+    }
+
+    // trick pop-ups
+    TextBox::new(
+        String::from(""),
+        16.0,
+        16.0,
+        String::from("start_font_small"),
+        String::from("start_font_small"),
+        8,
+        Some(20),
+        Some(3),
+    )
+    .to_tilemap(bus);
+    state.trick_text_map = bus.ppu.tile_maps.len() - 1;
+
+    // time trial best and last times
+    TextBox::new(
+        String::from(""),
+        320.0 - (16.0 * 8.0),
+        16.0,
+        String::from("start_font_small"),
+        String::from("start_font_small"),
+        8,
+        Some(16),
+        Some(2),
+    )
+    .to_tilemap(bus);
+    state.trial_text_map = bus.ppu.tile_maps.len() - 1;
+    display_trial_times(state, bus);
 
     TextBox::new(
         String::from("00:00.00"),
@@ -45,7 +87,9 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     state.player = Player::new(1, level.start_line);
     state.player.init(bus);
 
-    // Initialize Spyder targets
+    // Initialize Spyder targets and checkpoints
+    state.world.checkpoints.clear();
+    state.spyder.events.get_mut(&state.event).unwrap().targets.clear();
     {
         data_entity_handler(
             &bus.game_pak.scenes[state.current_scene].data_entities,
@@ -67,6 +111,9 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     bus.ppu.screen_state.map_max_y = bus.ppu.tile_maps[0].rows * bus.ppu.tile_maps[0].tile_height;
     bus.ppu.screen_state.scroll_y = 200;
 
+    // respawn when fallen off the bottom of the map
+    state.world.fall_limit = bus.ppu.screen_state.map_max_y as f32 - 96.0;
+
     state.check_game(bus);
 
     state.last_event_success = false;
@@ -86,24 +133,55 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
 
     match state.game {
         crate::game::state::GameMode::Buglympics => {
-            if !state.bl_finished {
+            state.stats.bl_frames += 1;
+            if !state.bl_finished && state.play_mode != PlayMode::Practice {
                 state.bl_timer += time_delta;
             }
 
+            // Time trial, keep the times and stay on the course
+            if state.play_mode == PlayMode::TimeTrial && state.player.finished && !state.bl_finished {
+                state
+                    .time_trials
+                    .entry(state.event.to_string())
+                    .or_insert_with(TimeTrialRecord::default)
+                    .add_time(state.bl_timer);
+                state.bl_finished = true;
+                display_trial_times(state, bus);
+            }
+
             // Check if medal worthy
-            if state.player.finished && !state.bl_finished {
+            if state.play_mode == PlayMode::Campaign
+                && state.player.finished
+                && !state.bl_finished
+            {
                 //println!("Finished at : {}", &clock_time);
                 let medals = state.buglympics.medals.get_mut(&state.event).unwrap();
-                medals.check_result(BuglympicsEventRecord {
+                let place = medals.check_result(BuglympicsEventRecord {
+                    name: state.player_name.to_string(),
                     nation: state.buglympics.nation.to_string(),
                     event: state.event.to_string(),
                     time: state.bl_timer,
+                    score: state.player.tricks.score,
                 });
                 state.bl_finished = true;
+                state.events.get_mut(&state.event).unwrap().bl_complete = true;
+
+                // a medal time gets a name before the ceremony
+                if let Some(place) = place {
+                    state.pending_record = Some((state.event.to_string(), place));
+                }
+
+                state.achievements.notify(&GameEvent::BuglympicsFinished {
+                    event: state.event.to_string(),
+                    nation: state.buglympics.nation.to_string(),
+                    jumps: state.player.jumps,
+                    place,
+                });
             }
         }
         crate::game::state::GameMode::Spyder => {
-            if !state.spy_finished {
+            state.stats.spy_frames += 1;
+            if !state.spy_finished && state.play_mode != PlayMode::Practice {
                 state.spy_timer -= time_delta;
             }
 
@@ -120,31 +198,38 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
                 }
             }
 
-            if all && !state.spy_finished {
-                state.spyder.results.insert(
-                    state.event.to_string(),
-                    crate::game::state::SpyderEventRecord {
-                        event: state.event.to_string(),
-                        time_remaining: state.spy_timer,
-                    },
-                );
+            if state.play_mode == PlayMode::Campaign && all && !state.spy_finished {
+                let record = spyder_record(state, true);
+                state.spyder.award_stars(&record);
+                state.achievements.notify(&GameEvent::SpyderFinished {
+                    record: record.clone(),
+                });
+                state.spyder.record_result(record);
 
                 state.spy_finished = true;
+                state.events.get_mut(&state.event).unwrap().spy_complete = true;
             }
 
             // Times up
-            if state.spy_timer < 0.0 && !state.spy_finished {
+            if state.play_mode == PlayMode::Campaign
+                && state.spy_timer < 0.0
+                && !state.spy_finished
+            {
                 state.last_event_success = false;
 
+                let record = spyder_record(state, false);
+                state.spyder.record_result(record);
+
                 // set this scene as complete
                 bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
 
-                state.current_scene = 4;
+                state.current_scene = results_scene(state);
             }
         }
     }
 
     display_timer(state, bus);
+    display_tricks(state, bus);
 
     /*
     Event Complete?
@@ -157,7 +242,7 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
         // set this scene as complete
         bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
 
-        state.current_scene = 4;
+        state.current_scene = results_scene(state);
     }
 
     /*
@@ -177,11 +262,12 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
 
     state.player.update(
         bus, 
-        &state.inputs, 
+        &state.input_state, 
         &state.game, 
         &state.world,
         &mut state.spyder.events.get_mut(&state.event).unwrap().targets,
-        finish_line
+        finish_line,
+        &mut state.stats,
     );
 
     // Camera
@@ -189,33 +275,69 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
         .screen_state
         .lerp(next_screen_pos, time_delta * 10.0);
 
-    // catch if fall off bottom of map
-    if state.player.transform.scene_y + 96.0 > bus.ppu.screen_state.map_max_y as f32 {
-        state.last_event_success = false;
+    // fell off the course, time penalty
+    if state.player.respawned && state.play_mode != PlayMode::Practice {
+        match state.game {
+            crate::game::state::GameMode::Buglympics => {
+                if !state.bl_finished {
+                    state.bl_timer += RESPAWN_PENALTY;
+                }
+            }
+            crate::game::state::GameMode::Spyder => {}
+        }
+    }
 
-        // set this scene as complete
-        bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
+    if state.play_mode == PlayMode::TimeTrial {
+        // instant restart
+        if state.input_state.pressed(InputCode::Confirm) {
+            bus.game_pak.scenes[state.current_scene].state = SceneState::INITIAL;
+        }
 
-        state.current_scene = 4;
-    }
+        // back to the course list
+        if state.input_state.pressed(InputCode::Swap) {
+            back_to_events(bus, state);
+        }
+    } else {
+        if state.play_mode == PlayMode::Practice {
+            practice_controls(bus, state);
+        }
 
-    // Game Hot Swap
-    if state.inputs.contains(&InputCode::Swap) {
-        state.swap_game(bus);
-        set_tile_attrs(state, false);
+        // Game Hot Swap
+        if state.input_state.pressed(InputCode::Swap) {
+            state.swap_game(bus);
+            set_tile_attrs(state, false);
+        }
     }
-    state.swap_cooldown += 1;
 
     state.scene_frames += 1;
 }
 
+// The event list was left running, start it over so it redraws
+pub fn back_to_events(bus: &mut LentSysBus, state: &mut GameState) {
+    bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
+    state.current_scene = 3;
+    bus.game_pak.scenes[state.current_scene].state = SceneState::INITIAL;
+}
+
+// the medal ceremony, by way of name entry for a new medal time
+pub fn results_scene(state: &GameState) -> usize {
+    match state.pending_record {
+        Some(_) => 11,
+        None => 4,
+    }
+}
+
 pub fn display_timer(state: &mut GameState, bus: &mut LentSysBus) {
     let clock_time: f32;
     let timer_map_idx = bus.ppu.tile_maps.len() - 1;
 
     match state.game {
+        crate::game::state::GameMode::Buglympics if state.play_mode == PlayMode::Practice => {
+            state.hit_text = String::from("PRACTICE");
+            clock_time = state.bl_timer;
+        }
         crate::game::state::GameMode::Buglympics => {
-            state.hit_text = String::from("");
+            state.hit_text = format!("PTS {}", state.player.tricks.score);
             clock_time = state.bl_timer;
         }
         crate::game::state::GameMode::Spyder => {
@@ -236,6 +358,94 @@ pub fn display_timer(state: &mut GameState, bus: &mut LentSysBus) {
     bus.ppu.tile_maps[timer_map_idx].update_text(time);
 }
 
+pub fn spyder_record(state: &GameState, success: bool) -> SpyderEventRecord {
+    let event = state.spyder.events.get(&state.event).unwrap();
+    SpyderEventRecord {
+        event: state.event.to_string(),
+        success,
+        time_remaining: state.spy_timer.max(0.0),
+        time_limit: event.time_limit,
+        targets_hit: event.targets.iter().filter(|tgt| tgt.hit).count(),
+        targets_total: event.targets.len(),
+        shots_fired: state.stats.shots_fired - state.event_stats.shots_fired,
+        shots_hit: state.stats.shots_hit - state.event_stats.shots_hit,
+    }
+}
+
+// Tap confirm to teleport to the next checkpoint or target,
+// hold it to go back to the event list
+pub fn practice_controls(bus: &mut LentSysBus, state: &mut GameState) {
+    // ignore a press carried over from the event list
+    let held = state.input_state.held_for(InputCode::Confirm);
+    if held == 60 && held <= state.scene_frames {
+        back_to_events(bus, state);
+    }
+
+    match state.input_state.released_after(InputCode::Confirm) {
+        Some(frames) if frames < 60 && frames <= state.scene_frames => {
+            let points = practice_points(state);
+            state.practice_point = (state.practice_point + 1) % points.len();
+
+            let point = points[state.practice_point];
+            state.player.safe_pos = point;
+            state.player.respawn();
+        }
+        _ => {}
+    }
+}
+
+// start line, then checkpoints, then targets
+pub fn practice_points(state: &GameState) -> Vec<[f32; 2]> {
+    let start_line = state.buglympics.events[&state.event].start_line;
+    let mut points = vec![[start_line[0] as f32, start_line[1] as f32]];
+
+    points.extend(state.world.checkpoints.iter().cloned());
+
+    for tgt in state.spyder.events[&state.event].targets.iter() {
+        points.push([tgt.transform.scene_x, tgt.transform.scene_y - 32.0]);
+    }
+
+    points
+}
+
+pub fn display_trial_times(state: &GameState, bus: &mut LentSysBus) {
+    if state.play_mode != PlayMode::TimeTrial {
+        return;
+    }
+
+    let format_time = |time: Option<f32>| match time {
+        Some(time) => format!("{:.2}", time),
+        None => String::from("--"),
+    };
+
+    let (last, best) = match state.time_trials.get(&state.event) {
+        Some(record) => (record.last, record.best),
+        None => (None, None),
+    };
+
+    bus.ppu.tile_maps[state.trial_text_map].update_text(format!(
+        "LAST {}\nBEST {}",
+        format_time(last),
+        format_time(best)
+    ));
+}
+
+pub fn display_tricks(state: &mut GameState, bus: &mut LentSysBus) {
+    let tricks = &mut state.player.tricks;
+
+    if tricks.popup_time > 0 {
+        tricks.popup_time -= 1;
+
+        let text = match state.game {
+            crate::game::state::GameMode::Buglympics if tricks.popup_time > 0 => {
+                tricks.popup.to_string()
+            }
+            _ => String::from(""),
+        };
+        bus.ppu.tile_maps[state.trick_text_map].update_text(text);
+    }
+}
+
 pub fn data_entity_handler(data_entities: &Vec<lentsys::ecs::DataEntity>, state: &mut GameState) {
     use crate::game::state::Target;
     for ent in data_entities.iter() {
@@ -266,6 +476,19 @@ pub fn data_entity_handler(data_entities: &Vec<lentsys::ecs::DataEntity>, state:
                     .targets
                     .push(target);
             }
+            "checkpoint" => {
+                let mut scene_x = 0.0;
+                let mut scene_y = 0.0;
+                for dc in ent.data_components.iter() {
+                    match dc.param_name.as_str() {
+                        "scene_x" => scene_x = dc.param_value.parse::<f32>().unwrap(),
+                        "scene_y" => scene_y = dc.param_value.parse::<f32>().unwrap(),
+                        _ => {}
+                    }
+                }
+
+                state.world.checkpoints.push([scene_x, scene_y]);
+            }
             _ => {}
         }
     }
@@ -0,0 +1,166 @@
+use lentsys::lentsys::LentSysBus;
+use lentsys::ui::text::{Text, TextBox};
+
+use crate::game::bindings::KeyBindings;
+use crate::game::input::InputCode;
+use crate::game::menu::Menu;
+use crate::game::sounds;
+use crate::game::state::GameState;
+
+const RESET: usize = 9;
+const BACK: usize = 10;
+const VISIBLE_ROWS: usize = 8;
+
+pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
+  state.event = String::from("controls");
+  state.rebinding = None;
+
+  state.menu = Menu {
+    name: String::from("Controls"),
+    screen_x: 0,
+    screen_y: 0,
+    options: binding_labels(&state.settings.key_bindings),
+    option_positions: Menu::grid_positions([32, 56], [0, 20], 1, VISIBLE_ROWS),
+    current_selection: 0,
+    confirmed: false,
+    text_tile_set_name: String::from("start_font_small"),
+    palette_name: String::from("start_font_small"),
+    font_size: 8,
+    cursor_tile_set_id: 2,
+    cursor_tile_id: 9,
+    cursor_offset: [-12, 0],
+    cursor_sprite_id: 0,
+    visible_rows: VISIBLE_ROWS,
+    // room for a label and three key names
+    text_width: 36,
+    ..Menu::default()
+  };
+
+  state.menu.load(bus);
+
+  let title = TextBox::new(
+    String::from("CONTROLS"),
+    16.0,
+    16.0,
+    String::from("start_font"),
+    String::from("start_font"),
+    16,
+    Some(20),
+    Some(1),
+  );
+
+  title.to_tilemap(bus);
+  let last_tm = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_tm].order = 1;
+
+  let help = TextBox::new(
+    String::from("ENTER ADD KEY  LEFT CLEAR"),
+    16.0,
+    36.0,
+    String::from("start_font_small"),
+    String::from("start_font_small"),
+    8,
+    Some(36),
+    Some(1),
+  );
+
+  help.to_tilemap(bus);
+  let last_tm = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_tm].order = 1;
+  state.controls_text_map = last_tm;
+
+  state.check_game(bus);
+}
+
+pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
+  if let Some(idx) = state.rebinding {
+    capture_key(bus, state, idx);
+    return;
+  }
+
+  if state.input_state.pressed(InputCode::Swap) {
+    state.swap_game(bus);
+  }
+
+  // left clears a binding, so the menu only gets up and down
+  let nav = state
+    .input_state
+    .without(&[InputCode::Left, InputCode::Right]);
+  state.menu.update_cursor(&nav, bus);
+  let selection = state.menu.current_selection;
+
+  if state.input_state.pressed(InputCode::Cancel) {
+    state.go_back(bus);
+    return;
+  }
+
+  if state.input_state.pressed(InputCode::Left) && selection < RESET {
+    state.settings.key_bindings.clear(selection);
+    sounds::play_effect(bus, sounds::SFX::Switch);
+    update_labels(bus, state);
+  }
+
+  if state.menu.confirmed {
+    match selection {
+      RESET => {
+        state.settings.key_bindings = KeyBindings::default();
+        update_labels(bus, state);
+      }
+      BACK => {
+        state.go_back(bus);
+      }
+      _ => {
+        // only keys pressed from here on count
+        state.captured_key = None;
+        state.rebinding = Some(selection);
+        bus.ppu.tile_maps[state.controls_text_map].update_text(format!(
+          "PRESS A KEY FOR {}  ESC CANCEL",
+          state.settings.key_bindings.list[selection].button.label()
+        ));
+      }
+    }
+  }
+  state.menu.confirmed = false;
+}
+
+fn capture_key(bus: &mut LentSysBus, state: &mut GameState, idx: usize) {
+  let key = match state.captured_key.take() {
+    Some(key) => key,
+    None => return,
+  };
+
+  if key != "Escape" {
+    state.settings.key_bindings.bind(idx, &key);
+    sounds::play_effect(bus, sounds::SFX::Select);
+  }
+
+  state.rebinding = None;
+  bus
+    .ppu
+    .tile_maps[state.controls_text_map]
+    .update_text(String::from("ENTER ADD KEY  LEFT CLEAR"));
+  update_labels(bus, state);
+}
+
+fn update_labels(bus: &mut LentSysBus, state: &mut GameState) {
+  state.menu.options = binding_labels(&state.settings.key_bindings);
+  state.menu.refresh(bus);
+}
+
+pub fn binding_labels(bindings: &KeyBindings) -> Vec<String> {
+  let mut labels: Vec<String> = bindings
+    .list
+    .iter()
+    .map(|binding| {
+      format!(
+        "{:<9}{}",
+        binding.button.label(),
+        binding.keys.join(" ").to_uppercase()
+      )
+    })
+    .collect();
+
+  labels.push(String::from("RESET DEFAULTS"));
+  labels.push(String::from("BACK"));
+  labels
+}
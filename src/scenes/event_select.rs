@@ -1,25 +1,40 @@
 use lentsys::lentsys::LentSysBus;
-use lentsys::ui::text::TextBox;
+use lentsys::ui::text::{Text, TextBox};
 use lentsys::game_pak::scene::SceneState;
 
 use crate::game::input::InputCode;
 use crate::game::menu::Menu;
-use crate::game::state::GameState;
+use crate::game::state::{GameMode, GameState, PlayMode};
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
-  state.input_cooldown = 15;
+
+  let options = vec![
+    String::from("CROSS-COUNTRY BIATHLON"),
+    String::from("DOWNHILL BIATHLON"),
+    String::from("CRAGGY BIATHLON"),
+  ];
+
+  // keep the cursor on the last event played
+  let current_selection = options
+    .iter()
+    .position(|event| *event == state.event)
+    .unwrap_or(0);
+
+  // locked events are greyed out and can't be entered
+  let disabled = options
+    .iter()
+    .map(|event| {
+      state.play_mode == PlayMode::Campaign && !state.campaign.is_unlocked(event, &state.events)
+    })
+    .collect();
 
   state.menu = Menu {
     name: String::from("Event Select"),
     screen_x: 0,
     screen_y: 0,
-    options: vec![
-      String::from("CROSS-COUNTRY BIATHLON"),
-      String::from("DOWNHILL BIATHLON"),
-      String::from("CRAGGY BIATHLON"),
-    ],
+    options,
     option_positions: vec![[48, 64], [48, 128], [48, 192]],
-    current_selection: 0,
+    current_selection,
     confirmed: false,
     text_tile_set_name: String::from("start_font_small"),
     palette_name: String::from("start_font_small"),
@@ -28,8 +43,8 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     cursor_tile_id: 9,
     cursor_sprite_id: 0,
     cursor_offset: [-12, 0],
-    input_time: 0,
-    input_threshold: 5,
+    disabled,
+    ..Menu::default()
   };
 
   state.menu.load(bus);
@@ -49,10 +64,53 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
   let last_tm = bus.ppu.tile_maps.len() - 1;
   bus.ppu.tile_maps[last_tm].order = 1;
 
+  // campaign markers
+  state.event_markers = vec![];
+  for (idx, _event) in state.menu.options.iter().enumerate() {
+    TextBox::new(
+      String::from(""),
+      state.menu.option_positions[idx][0] as f32,
+      state.menu.option_positions[idx][1] as f32 + 16.0,
+      String::from("start_font_small"),
+      String::from("start_font_small"),
+      8,
+      Some(12),
+      Some(1),
+    )
+    .to_tilemap(bus);
+
+    let last_idx = bus.ppu.tile_maps.len() - 1;
+    bus.ppu.tile_maps[last_idx].order = 1;
+    state.event_markers.push(last_idx);
+  }
+
+  // star total, follows the per event markers
+  TextBox::new(
+    String::from(""),
+    16.0,
+    40.0,
+    String::from("start_font_small"),
+    String::from("start_font_small"),
+    8,
+    Some(32),
+    Some(1),
+  )
+  .to_tilemap(bus);
+
+  let last_idx = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_idx].order = 1;
+  state.event_markers.push(last_idx);
+
+  // time trial only runs Buglympics
+  if state.play_mode == PlayMode::TimeTrial {
+    state.game = GameMode::Buglympics;
+  }
+
   state.check_game(bus);
+  update_markers(bus, state);
 
   match state.game {
-    crate::game::state::GameMode::Buglympics => {
+    GameMode::Buglympics => {
       let medal_places = ["GOLD", "SILVER", "BRONZE"];
       for (key, medal_standing) in state.buglympics.medals.iter() {
         for (place, medal) in medal_standing.medals.iter().enumerate() {
@@ -83,18 +141,21 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
         }
       }
     }
-    crate::game::state::GameMode::Spyder => {}
+    GameMode::Spyder => {}
   }
 }
 
 pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
-  if state.inputs.contains(&InputCode::Swap) && state.swap_cooldown > 8 {
+  if state.input_state.pressed(InputCode::Swap) && state.play_mode != PlayMode::TimeTrial {
     state.swap_game(bus);
+    update_markers(bus, state);
   }
 
-  state.menu.update_cursor(&state.inputs, bus);
+  state.menu.update_cursor(&state.input_state, bus);
 
-  if state.input_cooldown == 0 && state.menu.confirmed {
+  if state.input_state.pressed(InputCode::Cancel) {
+    state.go_back(bus);
+  } else if state.menu.confirmed {
     state.event = state.menu.options[state.menu.current_selection].to_string();
 
     state.current_scene = state.events.get(&state.event).unwrap().scene;
@@ -105,10 +166,50 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
   } else {
     state.menu.confirmed = false;
   }
+}
+
+// LOCKED / COMPLETE / stars marker under each event for the current game,
+// or the best time in time trial
+pub fn update_markers(bus: &mut LentSysBus, state: &GameState) {
+  for (idx, event) in state.menu.options.iter().enumerate() {
+    let text = if state.play_mode == PlayMode::TimeTrial {
+      match state.time_trials.get(event).and_then(|record| record.best) {
+        Some(best) => format!("BEST {:.2}", best),
+        None => String::from(""),
+      }
+    } else if state.play_mode == PlayMode::Campaign
+      && !state.campaign.is_unlocked(event, &state.events)
+    {
+      String::from("LOCKED")
+    } else {
+      let mapping = state.events.get(event).unwrap();
+      match state.game {
+        GameMode::Buglympics if mapping.bl_complete => String::from("COMPLETE"),
+        GameMode::Spyder if mapping.spy_complete => star_text(state, event),
+        _ => String::from(""),
+      }
+    };
 
-  if state.input_cooldown > 0 {
-    state.input_cooldown -= 1;
+    bus.ppu.tile_maps[state.event_markers[idx]].update_text(text);
   }
 
-  state.swap_cooldown += 1;
+  let total_text = match state.game {
+    _ if state.play_mode == PlayMode::Practice => String::from("PRACTICE"),
+    GameMode::Buglympics if state.play_mode == PlayMode::TimeTrial => String::from("TIME TRIAL"),
+    GameMode::Buglympics => String::from(""),
+    GameMode::Spyder => format!(
+      "STARS {}/{} - {} TO WIN",
+      state.spyder.total_stars(),
+      state.spyder.max_stars(),
+      state.spyder.stars_required
+    ),
+  };
+  let total_idx = state.event_markers[state.menu.options.len()];
+  bus.ppu.tile_maps[total_idx].update_text(total_text);
+}
+
+// earned stars as '*', the rest as '-'
+pub fn star_text(state: &GameState, event: &str) -> String {
+  let stars = *state.spyder.stars.get(event).unwrap_or(&0) as usize;
+  format!("{}{}", "*".repeat(stars), "-".repeat(3 - stars))
 }
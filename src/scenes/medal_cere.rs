@@ -1,12 +1,11 @@
 use lentsys::lentsys::LentSysBus;
-use lentsys::ui::text::TextBox;
+use lentsys::ui::text::{Text, TextBox};
 use lentsys::game_pak::scene::SceneState;
 use crate::game::input::InputCode;
 use crate::game::menu::Menu;
-use crate::game::state::GameState;
+use crate::game::state::{GameMode, GameState};
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
-  state.input_cooldown = 15;
 
   let medals = state.buglympics.medals.get(&state.event).unwrap();
 
@@ -15,9 +14,9 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     screen_x: 0,
     screen_y: 0,
     options: vec![
-      String::from(format!("GOLD\n{}", medals.medals[0].time)),
-      String::from(format!("SILVER\n{}", medals.medals[1].time)),
-      String::from(format!("BRONZE\n{}", medals.medals[2].time)),
+      String::from(format!("GOLD\n{}\n{}", medals.medals[0].name, medals.medals[0].time)),
+      String::from(format!("SILVER\n{}\n{}", medals.medals[1].name, medals.medals[1].time)),
+      String::from(format!("BRONZE\n{}\n{}", medals.medals[2].name, medals.medals[2].time)),
     ],
     option_positions: vec![[7 * 16, 12 * 16], [1 * 16, 12 * 16], [15 * 16, 12 * 16]],
     current_selection: 0,
@@ -29,8 +28,7 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     cursor_tile_id: 9,
     cursor_sprite_id: 0,
     cursor_offset: [-12, 0],
-    input_time: 0,
-    input_threshold: 30,
+    ..Menu::default()
   };
 
   state.menu.load(bus);
@@ -81,21 +79,40 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
   last_tm = bus.ppu.tile_maps.len() - 1;
   bus.ppu.tile_maps[last_tm].order = 1;
 
+  // Spyder debrief
+  state.spyder.debrief = debrief_text(state);
+
+  TextBox::new(
+    String::from(""),
+    16.0,
+    16.0,
+    String::from("start_font_small"),
+    String::from("title_screen_hh"),
+    8,
+    Some(32),
+    Some(6),
+  )
+  .to_tilemap(bus);
+  last_tm = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_tm].order = 1;
+
   // Check which game we're on
   state.event = String::from("medal_cere");
   state.check_game(bus);
+  swap_text(state, bus);
 }
 
 pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
   // Hot Swap
-  if state.inputs.contains(&InputCode::Swap) && state.swap_cooldown > 8 {
+  if state.input_state.pressed(InputCode::Swap) {
     state.swap_game(bus);
+    swap_text(state, bus);
   }
 
   // Menu inputs
-  state.menu.update_cursor(&state.inputs, bus);
+  state.menu.update_cursor(&state.input_state, bus);
 
-  if state.input_cooldown == 0 && state.menu.confirmed {
+  if state.menu.confirmed {
     // set this scene as complete
     bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
 
@@ -106,10 +123,49 @@ pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
   } else {
     state.menu.confirmed = false;
   }
+}
 
-  if state.input_cooldown > 0 {
-    state.input_cooldown -= 1;
+pub fn swap_text(state: &GameState, bus: &mut LentSysBus) {
+  let last_tm = bus.ppu.tile_maps.len() - 1;
+  match state.game {
+    GameMode::Buglympics => {
+      bus.ppu.tile_maps[last_tm].update_text(String::from(""));
+    }
+    GameMode::Spyder => {
+      bus.ppu.tile_maps[last_tm].update_text(state.spyder.debrief.to_string());
+    }
   }
+}
+
+// Mission debrief for the last Spyder attempt, records a new best if beaten
+pub fn debrief_text(state: &GameState) -> String {
+  let record = match state.spyder.results.get(&state.event) {
+    Some(record) => record.clone(),
+    None => return String::from("NO MISSION DATA"),
+  };
+
+  let best_text = match state.spyder.best.get(&state.event) {
+    Some(best) if !state.spyder.new_best => format!(
+      "BEST      {} {:.2}",
+      best.grade(),
+      best.time_remaining
+    ),
+    _ => String::from("NEW BEST!"),
+  };
 
-  state.swap_cooldown += 1;
+  format!(
+    "DEBRIEF\n\
+    TIME LEFT {:.2}\n\
+    DEVICES   {}/{}\n\
+    SHOTS     {} ACC {}%\n\
+    GRADE     {}\n\
+    {}",
+    record.time_remaining,
+    record.targets_hit,
+    record.targets_total,
+    record.shots_fired,
+    (record.accuracy() * 100.0) as u32,
+    record.grade(),
+    best_text
+  )
 }
@@ -4,4 +4,7 @@ pub mod medal_cere;
 pub mod victory;
 pub mod nation_select;
 pub mod attract_mode;
-pub mod biathlon;
\ No newline at end of file
+pub mod biathlon;
+pub mod options;
+pub mod controls;
+pub mod name_entry;
@@ -0,0 +1,145 @@
+use lentsys::lentsys::LentSysBus;
+use lentsys::ui::text::{Text, TextBox};
+use lentsys::game_pak::scene::SceneState;
+
+use crate::game::input::InputCode;
+use crate::game::menu::Menu;
+use crate::game::sounds;
+use crate::game::state::GameState;
+
+const CHARACTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const MAX_NAME: usize = 8;
+const COLUMNS: usize = 10;
+
+pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
+  // arcade style grid, with delete and end after the characters
+  let mut options: Vec<String> = CHARACTERS.chars().map(|c| c.to_string()).collect();
+  options.push(String::from("DEL"));
+  options.push(String::from("END"));
+
+  let slots = options.len();
+  state.menu = Menu {
+    name: String::from("NameEntry"),
+    screen_x: 0,
+    screen_y: 0,
+    options,
+    option_positions: Menu::grid_positions([48, 112], [24, 24], COLUMNS, slots),
+    current_selection: 0,
+    confirmed: false,
+    text_tile_set_name: String::from("start_font_small"),
+    palette_name: String::from("start_font_small"),
+    font_size: 8,
+    cursor_tile_set_id: 2,
+    cursor_tile_id: 9,
+    cursor_sprite_id: 0,
+    cursor_offset: [-12, 0],
+    columns: COLUMNS,
+    ..Menu::default()
+  };
+
+  state.menu.load(bus);
+
+  let place = match &state.pending_record {
+    Some((_, 0)) => "GOLD",
+    Some((_, 1)) => "SILVER",
+    _ => "BRONZE",
+  };
+
+  let banner = TextBox::new(
+    format!("NEW {} TIME", place),
+    16.0,
+    16.0,
+    String::from("start_font"),
+    String::from("start_font"),
+    16,
+    Some(20),
+    Some(1),
+  );
+
+  banner.to_tilemap(bus);
+  let last_tm = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_tm].order = 1;
+
+  // the name so far, starting from the last one entered
+  TextBox::new(
+    String::from(""),
+    48.0,
+    64.0,
+    String::from("start_font_small"),
+    String::from("start_font_small"),
+    8,
+    Some(20),
+    Some(1),
+  )
+  .to_tilemap(bus);
+
+  let last_tm = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_tm].order = 1;
+  state.name_text_map = last_tm;
+
+  show_name(bus, state);
+}
+
+pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
+  state.menu.update_cursor(&state.input_state, bus);
+
+  // cancel works as delete
+  if state.input_state.pressed(InputCode::Cancel) {
+    state.player_name.pop();
+    sounds::play_effect(bus, sounds::SFX::Switch);
+    show_name(bus, state);
+  }
+
+  if state.menu.confirmed {
+    state.menu.confirmed = false;
+
+    match state.menu.options[state.menu.current_selection].as_str() {
+      "DEL" => {
+        state.player_name.pop();
+      }
+      "END" => {
+        finish(bus, state);
+        return;
+      }
+      character => {
+        if state.player_name.len() < MAX_NAME {
+          state.player_name.push_str(character);
+        }
+
+        // full names jump to END
+        if state.player_name.len() == MAX_NAME {
+          state.menu.current_selection = state.menu.options.len() - 1;
+        }
+      }
+    }
+
+    show_name(bus, state);
+  }
+}
+
+fn show_name(bus: &mut LentSysBus, state: &GameState) {
+  let cursor = if state.player_name.len() < MAX_NAME { "_" } else { "" };
+  bus.ppu.tile_maps[state.name_text_map]
+    .update_text(format!("NAME {}{}", state.player_name, cursor));
+}
+
+// Put the name on the medal record and move on to the ceremony
+fn finish(bus: &mut LentSysBus, state: &mut GameState) {
+  if let Some((event, place)) = state.pending_record.take() {
+    let name = if state.player_name.is_empty() {
+      state.buglympics.nation.to_string()
+    } else {
+      state.player_name.to_string()
+    };
+
+    if let Some(medals) = state.buglympics.medals.get_mut(&event) {
+      if let Some(record) = medals.medals.get_mut(place) {
+        record.name = name;
+      }
+    }
+  }
+
+  // set this scene as complete
+  bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
+  state.current_scene = 4;
+}
@@ -1,23 +1,29 @@
 use lentsys::lentsys::LentSysBus;
 use lentsys::ui::text::{TextBox, Text};
-use lentsys::game_pak::scene::SceneState;
 use crate::game::input::InputCode;
 use crate::game::menu::Menu;
 use crate::game::state::{GameMode, GameState};
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
-  state.input_cooldown = 15;
+  let options = vec![
+    String::from("ANTARTICA"),
+    String::from("EAST ARACHNYLVANIA"),
+    String::from("REP. OF WORMSTRALIA"),
+  ];
+
+  // coming back, keep the nation already picked
+  let current_selection = options
+    .iter()
+    .position(|nation| *nation == state.buglympics.nation)
+    .unwrap_or(0);
+
   state.menu = Menu {
     name: String::from("NationSelect"),
     screen_x: 0,
     screen_y: 0,
-    options: vec![
-      String::from("ANTARTICA"),
-      String::from("EAST ARACHNYLVANIA"),
-      String::from("REP. OF WORMSTRALIA"),
-    ],
+    options,
     option_positions: vec![[16, 64], [16, 128], [16, 192]],
-    current_selection: 0,
+    current_selection,
     confirmed: false,
     text_tile_set_name: String::from("start_font_small"),
     palette_name: String::from("start_font_small"),
@@ -26,8 +32,7 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     cursor_tile_id: 9,
     cursor_sprite_id: 0,
     cursor_offset: [-12, 0],
-    input_time: 0,
-    input_threshold: 5,
+    ..Menu::default()
   };
 
   state.menu.load(bus);
@@ -50,36 +55,29 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
   state.check_game(bus);
   swap_text(&state.game, bus);
 
-  state.menu.update_cursor(&state.inputs, bus);
+  state.menu.update_cursor(&state.input_state, bus);
 }
 
 pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
 
-  state.menu.update_cursor(&state.inputs, bus);
+  state.menu.update_cursor(&state.input_state, bus);
   
-  if state.inputs.contains(&InputCode::Swap) && state.swap_cooldown > 8 {
+  if state.input_state.pressed(InputCode::Swap) {
     state.swap_game(bus);
     swap_text(&state.game, bus);
   }
 
-  if state.input_cooldown == 0 && state.menu.confirmed {
+  if state.input_state.pressed(InputCode::Cancel) {
+    state.go_back(bus);
+  } else if state.menu.confirmed {
     
     state.buglympics.nation = state.menu.options[state.menu.current_selection].to_string();
 
-    // set this scene as complete
-    bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
-
-    state.current_scene = 3;
+    state.go_to(bus, 3);
   
   } else {
     state.menu.confirmed = false;
   }
-
-  if state.input_cooldown > 0 {
-    state.input_cooldown -= 1;
-  }
-
-  state.swap_cooldown += 1;
 }
 
 pub fn swap_text(game: &GameMode, bus: &mut LentSysBus) {
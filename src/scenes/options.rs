@@ -0,0 +1,174 @@
+use lentsys::lentsys::LentSysBus;
+use lentsys::ui::text::TextBox;
+
+use crate::game::input::InputCode;
+use crate::game::menu::{Menu, MenuValue};
+use crate::game::settings::{Settings, MAX_DEAD_ZONE, MAX_VOLUME, MAX_WINDOW_SCALE};
+use crate::game::state::{GameMode, GameState};
+
+const CONTROLS: usize = 7;
+const BACK: usize = 8;
+// percent per dead zone choice
+const DEAD_ZONE_STEP: u8 = 10;
+
+pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
+  state.event = String::from("options");
+
+  state.menu = Menu {
+    name: String::from("Options"),
+    screen_x: 0,
+    screen_y: 0,
+    options: vec![
+      String::from("MASTER VOLUME"),
+      String::from("MUSIC VOLUME"),
+      String::from("SFX VOLUME"),
+      String::from("WINDOW SCALE"),
+      String::from("FULLSCREEN"),
+      String::from("DEFAULT GAME"),
+      String::from("DEAD ZONE"),
+      String::from("CONTROLS"),
+      String::from("BACK"),
+    ],
+    option_positions: vec![
+      [32, 56],
+      [32, 72],
+      [32, 88],
+      [32, 112],
+      [32, 128],
+      [32, 152],
+      [32, 176],
+      [32, 192],
+      [32, 216],
+    ],
+    current_selection: 0,
+    confirmed: false,
+    text_tile_set_name: String::from("start_font_small"),
+    palette_name: String::from("start_font_small"),
+    font_size: 8,
+    cursor_tile_set_id: 2,
+    cursor_tile_id: 9,
+    cursor_sprite_id: 0,
+    cursor_offset: [-12, 0],
+    values: setting_values(&state.settings),
+    label_width: 15,
+    ..Menu::default()
+  };
+
+  state.menu.load(bus);
+
+  let instruct = TextBox::new(
+    String::from("OPTIONS"),
+    16.0,
+    16.0,
+    String::from("start_font"),
+    String::from("start_font"),
+    16,
+    Some(20),
+    Some(1),
+  );
+
+  instruct.to_tilemap(bus);
+  let last_tm = bus.ppu.tile_maps.len() - 1;
+  bus.ppu.tile_maps[last_tm].order = 1;
+
+  state.check_game(bus);
+}
+
+pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
+  if state.input_state.pressed(InputCode::Swap) {
+    state.swap_game(bus);
+  }
+
+  state.menu.update_cursor(&state.input_state, bus);
+
+  if state.menu.changed.is_some() {
+    apply_values(&mut state.settings, &state.menu);
+  }
+
+  if state.menu.confirmed && state.menu.current_selection == CONTROLS {
+    state.go_to(bus, 10);
+  } else if (state.menu.confirmed && state.menu.current_selection == BACK)
+    || state.input_state.pressed(InputCode::Cancel)
+  {
+    state.settings_changed = true;
+    state.go_back(bus);
+  } else {
+    state.menu.confirmed = false;
+  }
+}
+
+pub fn setting_values(settings: &Settings) -> Vec<Option<MenuValue>> {
+  let volume = |value| {
+    Some(MenuValue::Slider {
+      value,
+      min: 0,
+      max: MAX_VOLUME,
+    })
+  };
+
+  vec![
+    volume(settings.master_volume),
+    volume(settings.music_volume),
+    volume(settings.sfx_volume),
+    Some(MenuValue::Choice {
+      index: (settings.window_scale - 1) as usize,
+      choices: (1..=MAX_WINDOW_SCALE).map(|scale| format!("{}X", scale)).collect(),
+    }),
+    Some(MenuValue::Toggle(settings.fullscreen)),
+    Some(MenuValue::Choice {
+      index: match settings.default_game {
+        GameMode::Buglympics => 0,
+        GameMode::Spyder => 1,
+      },
+      choices: vec![String::from("BUGLYMPICS"), String::from("SPYDER")],
+    }),
+    Some(MenuValue::Choice {
+      index: ((settings.dead_zone + DEAD_ZONE_STEP / 2) / DEAD_ZONE_STEP) as usize,
+      choices: (0..=MAX_DEAD_ZONE)
+        .step_by(DEAD_ZONE_STEP as usize)
+        .map(|percent| format!("{}%", percent))
+        .collect(),
+    }),
+    None,
+    None,
+  ]
+}
+
+// Copy the menu's values back into the settings
+pub fn apply_values(settings: &mut Settings, menu: &Menu) {
+  let volume = |idx| match menu.value(idx) {
+    Some(MenuValue::Slider { value, .. }) => Some(*value),
+    _ => None,
+  };
+
+  if let Some(value) = volume(0) {
+    settings.master_volume = value;
+  }
+
+  if let Some(value) = volume(1) {
+    settings.music_volume = value;
+  }
+
+  if let Some(value) = volume(2) {
+    settings.sfx_volume = value;
+  }
+
+  if let Some(MenuValue::Choice { index, .. }) = menu.value(3) {
+    settings.window_scale = *index as u8 + 1;
+  }
+
+  if let Some(MenuValue::Toggle(on)) = menu.value(4) {
+    settings.fullscreen = *on;
+  }
+
+  if let Some(MenuValue::Choice { index, .. }) = menu.value(5) {
+    settings.default_game = match index {
+      1 => GameMode::Spyder,
+      _ => GameMode::Buglympics,
+    };
+  }
+
+  if let Some(MenuValue::Choice { index, .. }) = menu.value(6) {
+    settings.dead_zone = *index as u8 * DEAD_ZONE_STEP;
+  }
+}
@@ -1,16 +1,20 @@
 use lentsys::lentsys::LentSysBus;
-use lentsys::game_pak::scene::SceneState;
 use crate::game::input::InputCode;
 use crate::game::menu::Menu;
-use crate::game::state::{GameState};
+use crate::game::state::{GameState, PlayMode};
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
     state.menu = Menu {
         name: String::from("MainMenu"),
         screen_x: 0,
         screen_y: 0,
-        options: vec![String::from("PRESS ENTER")],
-        option_positions: vec![[112, 176]],
+        options: vec![
+            String::from("PRESS ENTER"),
+            String::from("TIME TRIAL"),
+            String::from("PRACTICE"),
+            String::from("OPTIONS"),
+        ],
+        option_positions: vec![[112, 176], [112, 192], [112, 208], [112, 224]],
         current_selection: 0,
         confirmed: false,
         text_tile_set_name: String::from("start_font_small"),
@@ -20,8 +24,7 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
         cursor_tile_id: 10,
         cursor_sprite_id: 0,
         cursor_offset: [-16, 0],
-        input_time: 0,
-        input_threshold: 30,
+        ..Menu::default()
     };
 
     state.menu.load(bus);
@@ -29,24 +32,37 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
 
 pub fn update(bus: &mut LentSysBus, state: &mut GameState){
 
-    if state.inputs.contains(&InputCode::Swap) {
+    if state.input_state.pressed(InputCode::Swap) {
 
         state.swap_game(bus);
     
     }
 
-    if state.inputs.contains(&InputCode::Confirm) {
-        
-        state.menu.confirmed = true;
-        
-        // set this scene as complete
-        bus.game_pak.scenes[state.current_scene].state = SceneState::COMPLETE;
+    state.menu.update_cursor(&state.input_state, bus);
 
-        // set next scene
-        state.current_scene = 1;
+    if state.menu.confirmed {
 
-    }
+        // back out to the title from wherever this leads
+        state.scene_history.clear();
 
-    state.swap_cooldown += 1;
+        match state.menu.current_selection {
+            // time trial skips the story and goes straight to the courses
+            1 => {
+                state.play_mode = PlayMode::TimeTrial;
+                state.go_to(bus, 3);
+            }
+            2 => {
+                state.play_mode = PlayMode::Practice;
+                state.go_to(bus, 3);
+            }
+            3 => {
+                state.go_to(bus, 9);
+            }
+            _ => {
+                state.play_mode = PlayMode::Campaign;
+                state.go_to(bus, 1);
+            }
+        }
 
+    }
 }
@@ -3,11 +3,10 @@ use lentsys::ui::text::TextBox;
 
 use crate::game::cutscene::Shot;
 use crate::game::input::InputCode;
-use crate::game::state::GameMode;
 use crate::game::state::GameState;
+use crate::game::stats::stats_table;
 
 pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
-  state.input_cooldown = 15;
   state.event = String::from("title_screen"); //use same swap logic
   state.bl_shots = vec![Shot {
     tile_map_name: String::from("cut_01_arrival"),
@@ -51,35 +50,33 @@ pub fn init(bus: &mut LentSysBus, state: &mut GameState) {
 
   state.current_shot = 0;
 
-  match &state.game {
-    GameMode::Buglympics => {
-      state.bl_shots[state.current_shot].load(bus);
-    }
-    GameMode::Spyder => {
-      state.spyder_shots[state.current_shot].load(bus);
-    }
-  }
+  state.load_shot(bus);
+  show_stats(bus, state);
 }
 
 pub fn update(bus: &mut LentSysBus, state: &mut GameState) {
   
   // swap
-  if state.inputs.contains(&InputCode::Swap) && state.swap_cooldown > 8 {
+  if state.input_state.pressed(InputCode::Swap) {
     state.swap_game(bus);
 
-    match &state.game {
-      GameMode::Buglympics => {
-        state.bl_shots[state.current_shot].load(bus);
-      }
-      GameMode::Spyder => {
-        state.spyder_shots[state.current_shot].load(bus);
-      }
-    }
+    state.load_shot(bus);
+    show_stats(bus, state);
   }
+}
 
-  state.swap_cooldown += 1;
-
-  if state.input_cooldown > 0 {
-    state.input_cooldown -= 1;
-  }
+// shots flush the ppu, so the table is drawn again after each load
+pub fn show_stats(bus: &mut LentSysBus, state: &GameState) {
+  let lifetime = state.lifetime_stats.combined(&state.stats);
+  TextBox::new(
+    stats_table(&state.stats, &lifetime),
+    32.0,
+    16.0,
+    String::from("start_font_small"),
+    String::from("start_font_small"),
+    8,
+    Some(32),
+    Some(9),
+  )
+  .to_tilemap(bus);
 }